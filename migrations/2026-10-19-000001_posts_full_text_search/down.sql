@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX ix_posts_post_content_tsv;
+
+ALTER TABLE posts
+DROP COLUMN post_content_tsv;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE posts
+ADD COLUMN post_content_tsv tsvector NOT NULL
+GENERATED ALWAYS AS (to_tsvector('english', post_content)) STORED;
+
+CREATE INDEX ix_posts_post_content_tsv ON posts USING GIN (post_content_tsv);
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX ix_posts_created_at;
+DROP INDEX ix_users_created_at;
+
+DROP TRIGGER set_updated_at ON posts;
+DROP TRIGGER set_updated_at ON users;
+
+ALTER TABLE posts
+DROP COLUMN created_at,
+DROP COLUMN updated_at;
+
+ALTER TABLE users
+DROP COLUMN created_at,
+DROP COLUMN updated_at;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+
+ALTER TABLE posts
+ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+
+SELECT diesel_manage_updated_at('users');
+SELECT diesel_manage_updated_at('posts');
+
+CREATE INDEX ix_users_created_at ON users(created_at);
+CREATE INDEX ix_posts_created_at ON posts(created_at);
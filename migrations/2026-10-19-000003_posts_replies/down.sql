@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX ix_posts_root_id;
+DROP INDEX ix_posts_parent_id;
+
+ALTER TABLE posts
+DROP CONSTRAINT ck_posts_reply_has_root,
+DROP COLUMN reply_count,
+DROP COLUMN root_id,
+DROP COLUMN parent_id;
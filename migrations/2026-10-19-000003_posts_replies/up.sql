@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE posts
+ADD COLUMN parent_id uuid REFERENCES posts(id) ON DELETE CASCADE,
+ADD COLUMN root_id uuid REFERENCES posts(id) ON DELETE CASCADE,
+ADD COLUMN reply_count int NOT NULL DEFAULT 0,
+ADD CONSTRAINT ck_posts_reply_has_root CHECK ((parent_id IS NULL) = (root_id IS NULL));
+
+CREATE INDEX ix_posts_parent_id ON posts(parent_id);
+CREATE INDEX ix_posts_root_id ON posts(root_id);
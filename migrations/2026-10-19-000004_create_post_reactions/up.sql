@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE post_reactions (
+	post_id uuid not null references posts(id) ON DELETE CASCADE,
+	user_id int not null references users(id) ON DELETE CASCADE,
+	reaction varchar(16) not null CHECK (reaction IN ('like', 'love', 'laugh', 'wow', 'sad')),
+	created_at timestamptz not null default now(),
+
+	PRIMARY KEY (post_id, user_id, reaction)
+);
+
+CREATE INDEX ix_post_reactions_user_id ON post_reactions(user_id);
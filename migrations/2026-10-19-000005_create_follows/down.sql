@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX ix_posts_user_id_created_at;
+DROP TABLE follows;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE follows (
+	follower_id int not null references users(id) ON DELETE CASCADE,
+	followee_id int not null references users(id) ON DELETE CASCADE,
+	created_at timestamptz not null default now(),
+
+	PRIMARY KEY (follower_id, followee_id),
+	CHECK (follower_id <> followee_id)
+);
+
+-- the primary key covers "who does X follow", this covers "who follows X"
+CREATE INDEX ix_follows_followee_id ON follows(followee_id, follower_id);
+
+-- home feed keyset pagination
+CREATE INDEX ix_posts_user_id_created_at ON posts(user_id, created_at DESC, id DESC);
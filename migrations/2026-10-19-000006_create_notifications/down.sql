@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE notifications;
+
+ALTER TABLE users
+DROP COLUMN handle;
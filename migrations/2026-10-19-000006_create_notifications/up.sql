@@ -0,0 +1,16 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN handle varchar(32) UNIQUE;
+
+CREATE TABLE notifications (
+	id uuid not null default uuid_generate_v7() PRIMARY KEY,
+	user_id int not null references users(id) ON DELETE CASCADE,
+	kind varchar(16) not null CHECK (kind IN ('mention', 'reply', 'reaction', 'follow')),
+	actor_id int not null references users(id) ON DELETE CASCADE,
+	post_id uuid references posts(id) ON DELETE CASCADE,
+	created_at timestamptz not null default now(),
+	read_at timestamptz
+);
+
+CREATE INDEX ix_notifications_user_id_created_at ON notifications(user_id, created_at DESC);
+CREATE INDEX ix_notifications_unread ON notifications(user_id) WHERE read_at IS NULL;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- rendered and sanitized markdown of post_content, written by the app; NULL
+-- for posts stored before bodies were rendered
+ALTER TABLE posts ADD COLUMN post_content_html TEXT;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE post_attachments (
+	-- generated by the app, which needs it for the blob keys before inserting
+	id uuid not null PRIMARY KEY,
+	post_id uuid not null references posts(id) ON DELETE CASCADE,
+	content_type varchar(32) not null,
+	byte_size int not null,
+	width int not null,
+	height int not null,
+	blob_key text not null,
+	thumb_key text not null,
+	created_at timestamptz not null default now()
+);
+
+CREATE INDEX ix_post_attachments_post_id ON post_attachments(post_id, id);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- sha256 of the uploaded avatar, its resized versions are stored under
+-- avatars/<hash>/<size>.png; NULL shows an identicon
+ALTER TABLE users ADD COLUMN avatar_hash varchar(64);
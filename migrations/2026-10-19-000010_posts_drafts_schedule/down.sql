@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX ix_posts_unpublished_user_id;
+DROP INDEX ix_posts_due;
+
+-- unpublished posts never existed as far as the old schema is concerned
+DELETE FROM posts WHERE status <> 'published';
+
+ALTER TABLE posts
+DROP CONSTRAINT posts_scheduled_publish_at,
+DROP COLUMN publish_at,
+DROP COLUMN status;
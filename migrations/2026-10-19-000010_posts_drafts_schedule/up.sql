@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- only published posts are visible to anyone but their author; the
+-- scheduler publishes scheduled ones once publish_at has passed
+ALTER TABLE posts
+ADD COLUMN status varchar(16) not null default 'published'
+	CHECK (status IN ('draft', 'scheduled', 'published')),
+ADD COLUMN publish_at timestamptz,
+ADD CONSTRAINT posts_scheduled_publish_at CHECK (status <> 'scheduled' OR publish_at IS NOT NULL);
+
+-- what the scheduler polls for
+CREATE INDEX ix_posts_due ON posts(publish_at) WHERE status = 'scheduled';
+
+-- an author's drafts
+CREATE INDEX ix_posts_unpublished_user_id ON posts(user_id, updated_at DESC) WHERE status <> 'published';
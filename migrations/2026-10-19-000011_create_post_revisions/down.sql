@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE post_revisions;
+
+ALTER TABLE posts
+DROP COLUMN edited_at;
+
+ALTER TABLE users
+DROP COLUMN is_moderator;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN is_moderator boolean not null default false;
+
+ALTER TABLE posts
+ADD COLUMN edited_at timestamptz;
+
+-- a post as it was before `edited_by` changed it at `created_at`
+CREATE TABLE post_revisions (
+	id uuid not null default uuid_generate_v7() PRIMARY KEY,
+	post_id uuid not null references posts(id) ON DELETE CASCADE,
+	post_content text not null,
+	tags text[] not null default '{}',
+	edited_by int not null references users(id) ON DELETE CASCADE,
+	created_at timestamptz not null default now()
+);
+
+CREATE INDEX ix_post_revisions_post_id_created_at ON post_revisions(post_id, created_at);
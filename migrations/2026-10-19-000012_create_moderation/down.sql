@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE moderation_actions;
+DROP TABLE post_reports;
+
+ALTER TABLE users
+DROP COLUMN suspended_until;
+
+ALTER TABLE posts
+DROP COLUMN hidden_at;
@@ -0,0 +1,33 @@
+-- Your SQL goes here
+ALTER TABLE posts
+ADD COLUMN hidden_at timestamptz;
+
+ALTER TABLE users
+ADD COLUMN suspended_until timestamptz;
+
+CREATE TABLE post_reports (
+	id uuid not null default uuid_generate_v7() PRIMARY KEY,
+	post_id uuid not null references posts(id) ON DELETE CASCADE,
+	reporter_id int not null references users(id) ON DELETE CASCADE,
+	reason text not null CHECK (length(reason) BETWEEN 1 AND 500),
+	created_at timestamptz not null default now(),
+	resolved_at timestamptz
+);
+
+-- one open report per user and post
+CREATE UNIQUE INDEX ux_post_reports_open ON post_reports(post_id, reporter_id) WHERE resolved_at IS NULL;
+CREATE INDEX ix_post_reports_open_created_at ON post_reports(created_at) WHERE resolved_at IS NULL;
+
+-- the audit log, kept when the post or the users are gone
+CREATE TABLE moderation_actions (
+	id uuid not null default uuid_generate_v7() PRIMARY KEY,
+	moderator_id int references users(id) ON DELETE SET NULL,
+	kind varchar(16) not null CHECK (kind IN ('hide', 'delete', 'suspend', 'dismiss')),
+	post_id uuid,
+	target_user_id int references users(id) ON DELETE SET NULL,
+	reason text,
+	suspended_until timestamptz,
+	created_at timestamptz not null default now()
+);
+
+CREATE INDEX ix_moderation_actions_created_at ON moderation_actions(created_at DESC);
@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM moderation_actions WHERE kind = 'approve';
+
+ALTER TABLE moderation_actions
+DROP CONSTRAINT moderation_actions_kind_check,
+ADD CONSTRAINT moderation_actions_kind_check CHECK (kind IN ('hide', 'delete', 'suspend', 'dismiss'));
+
+DROP INDEX ix_posts_held_created_at;
+
+-- held posts never existed as far as the old schema is concerned
+DELETE FROM posts WHERE status = 'held';
+
+ALTER TABLE posts
+DROP COLUMN held_reason,
+DROP CONSTRAINT posts_status_check,
+ADD CONSTRAINT posts_status_check CHECK (status IN ('draft', 'scheduled', 'published'));
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+-- held posts wait in the moderation queue for a moderator to approve them
+ALTER TABLE posts
+DROP CONSTRAINT posts_status_check,
+ADD CONSTRAINT posts_status_check CHECK (status IN ('draft', 'scheduled', 'published', 'held')),
+ADD COLUMN held_reason text;
+
+CREATE INDEX ix_posts_held_created_at ON posts(created_at) WHERE status = 'held';
+
+ALTER TABLE moderation_actions
+DROP CONSTRAINT moderation_actions_kind_check,
+ADD CONSTRAINT moderation_actions_kind_check CHECK (kind IN ('hide', 'delete', 'suspend', 'dismiss', 'approve'));
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- buckets of `middleware::rate_limit` shared by every replica; losing them
+-- in a crash only resets the limits, so skip the WAL
+CREATE UNLOGGED TABLE rate_limits (
+    key text PRIMARY KEY,
+    -- when the bucket is full again, see `PgRateLimitStore`
+    tat timestamptz NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- a reaction tells the author once, however often it is taken back and given
+-- again; `NotificationService::notify` skips the ones already stored
+DELETE FROM notifications a
+USING notifications b
+WHERE a.kind = 'reaction' AND b.kind = 'reaction'
+	AND a.user_id = b.user_id AND a.actor_id = b.actor_id AND a.post_id = b.post_id
+	AND a.id > b.id;
+
+CREATE UNIQUE INDEX ux_notifications_reaction ON notifications(user_id, actor_id, post_id)
+WHERE kind = 'reaction';
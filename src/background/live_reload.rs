@@ -0,0 +1,121 @@
+//! Development only: reloads the templates when they change and tells the
+//! open pages to refresh, see `middleware::live_reload` for the browser side.
+//!
+//! Saving a file makes `notify` report several events (editors truncate,
+//! write and rename), so events are collected until the files have been
+//! quiet for `DEBOUNCE` and handled as a single change.
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use tera::Tera;
+use tokio::sync::{RwLock, broadcast, mpsc};
+use tracing::{error, info, warn};
+
+use crate::config::Env;
+use crate::templating;
+
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// What the pages have to do about a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reload {
+    Page,
+    /// only stylesheets changed, they are swapped without losing the state of
+    /// the page
+    Css,
+}
+
+#[derive(Clone)]
+pub struct LiveReload {
+    tx: broadcast::Sender<Reload>,
+}
+
+impl LiveReload {
+    /// Watches `templates` and the built `assets`. Watching stops when the
+    /// returned watcher is dropped.
+    pub fn watch(
+        tera: Arc<RwLock<Tera>>,
+        templates: &Path,
+        assets: &Path,
+    ) -> anyhow::Result<(Self, notify::RecommendedWatcher)> {
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(e) if is_relevant(&e) => {
+                    let _ = events_tx.send(e.paths);
+                }
+                Err(e) => error!(%e, "issue with watching files"),
+                _ => {}
+            })?;
+        watcher.watch(templates, RecursiveMode::Recursive)?;
+        // missing until the first Parcel build
+        if let Err(e) = watcher.watch(assets, RecursiveMode::Recursive) {
+            warn!(%e, path = %assets.display(), "not watching assets");
+        }
+
+        let (tx, _) = broadcast::channel(16);
+        tokio::spawn(debounce(events_rx, tera, templates.to_owned(), tx.clone()));
+        Ok((Self { tx }, watcher))
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Reload> {
+        self.tx.subscribe()
+    }
+}
+
+fn is_relevant(event: &notify::Event) -> bool {
+    let changed = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    );
+    changed && event.paths.iter().any(|p| !is_scratch_file(p))
+}
+
+// swap and backup files of editors
+fn is_scratch_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    name.starts_with('.') || name.ends_with('~') || name.ends_with(".swp")
+}
+
+async fn debounce(
+    mut events: mpsc::UnboundedReceiver<Vec<PathBuf>>,
+    tera: Arc<RwLock<Tera>>,
+    templates: PathBuf,
+    tx: broadcast::Sender<Reload>,
+) {
+    while let Some(mut paths) = events.recv().await {
+        while let Ok(Some(more)) = tokio::time::timeout(DEBOUNCE, events.recv()).await {
+            paths.extend(more);
+        }
+        paths.retain(|p| !is_scratch_file(p));
+        paths.sort();
+        paths.dedup();
+        info!(?paths, "files changed");
+
+        let templates_changed = paths.iter().any(|p| p.starts_with(&templates));
+        if templates_changed {
+            // keeps serving the old templates until the broken one is fixed
+            match templating::load(&Env::Development) {
+                Ok(reloaded) => *tera.write().await = reloaded,
+                Err(e) => {
+                    error!(error = ?e, "templates not reloaded");
+                    continue;
+                }
+            }
+        }
+
+        let only_css = !templates_changed
+            && paths
+                .iter()
+                .all(|p| p.extension().is_some_and(|ext| ext == "css"));
+        // nobody listening is fine
+        let _ = tx.send(if only_css { Reload::Css } else { Reload::Page });
+    }
+}
@@ -1 +1,3 @@
+pub mod live_reload;
+pub mod post_scheduler;
 pub mod posts_broker;
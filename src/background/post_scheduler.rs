@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use macros::ert;
+use tokio::time::MissedTickBehavior;
+use tracing::{Instrument, Span, error, info};
+
+use crate::services::announce::announce_post;
+use crate::services::notifications::NotificationServiceDb;
+use crate::services::posts::{PostService, PostServiceDb};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+const BATCH_SIZE: i64 = 50;
+
+/// Publishes scheduled posts once their `publish_at` has passed.
+///
+/// The schedule lives in the `posts` table, so nothing is lost on restart:
+/// posts that came due while the app was down go out on the first tick.
+/// Several replicas can run a scheduler at once, each post is claimed by
+/// exactly one of them (see `PostService::publish_due`).
+pub struct PostScheduler {
+    span: Option<Span>,
+    post_svc: PostServiceDb,
+    notify_svc: NotificationServiceDb,
+    q_pool: deadpool_lapin::Pool,
+}
+
+impl PostScheduler {
+    pub fn new(
+        post_svc: PostServiceDb,
+        notify_svc: NotificationServiceDb,
+        q_pool: deadpool_lapin::Pool,
+    ) -> Self {
+        Self {
+            span: None,
+            post_svc,
+            notify_svc,
+            q_pool,
+        }
+    }
+
+    pub fn instrument(mut self, span: Span) -> Self {
+        self.span.replace(span);
+        self
+    }
+
+    /// Publishes everything that is due, a batch at a time.
+    async fn tick(&self) -> Result<(), Error> {
+        loop {
+            let due = self.post_svc.publish_due(BATCH_SIZE).await?;
+            if due.is_empty() {
+                return Ok(());
+            }
+            info!(n = due.len(), "publishing scheduled posts");
+
+            let ids: Vec<_> = due.iter().map(|p| p.id).collect();
+            let mut attachments = self.post_svc.attachments(&ids).await?;
+            for post in &due {
+                // already published, a broker hiccup only costs the live update
+                let _ = announce_post(
+                    &self.q_pool,
+                    &self.notify_svc,
+                    post,
+                    attachments.remove(&post.id).unwrap_or_default(),
+                )
+                .await
+                .inspect_err(ert!());
+            }
+            if (due.len() as i64) < BATCH_SIZE {
+                return Ok(());
+            }
+        }
+    }
+
+    pub async fn run(mut self) -> Result<(), Error> {
+        let span = self.span.take().unwrap_or_else(Span::current);
+        async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let _ = self.tick().await.inspect_err(ert!());
+            }
+        }
+        .instrument(span)
+        .await
+    }
+}
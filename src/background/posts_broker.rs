@@ -1,38 +1,130 @@
 #![allow(unused)]
 
-use std::{any::type_name, fmt::Debug, hash::Hash, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    any::type_name, collections::HashSet, fmt::Debug, hash::Hash, str::FromStr, sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Error;
 use futures::{StreamExt, TryStreamExt};
-use futures_util::{future, Future};
+use futures_util::{Future, future};
 use lapin::{
-    options::{BasicConsumeOptions, QueueBindOptions},
+    BasicProperties,
+    options::{BasicConsumeOptions, BasicPublishOptions, QueueBindOptions},
     types::{FieldTable, ShortString},
 };
 use serde::{Deserialize, Serialize};
 
 use dashmap;
-use tracing::{error, info, info_span, instrument, warn, Instrument, Span};
+use tracing::{Instrument, Span, error, info, info_span, instrument, warn};
 use uuid::Uuid;
 
-use macros::ert;
+use crate::config::PostsBrokerCfg;
 use crate::error::AppError;
+use crate::models::attachment::Attachment;
+use crate::models::notification::NotificationView;
+use crate::models::post::Post;
+use crate::models::reaction::ReactionCounts;
+use macros::ert;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PostsBrokerConfig {
-    n_workers: u32,
+/// What goes through the `posts` queue.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PostsEvent {
+    Created {
+        post: Post,
+        #[serde(default)]
+        attachments: Vec<Attachment>,
+    },
+    /// the reaction counters of a post changed
+    Reactions {
+        post_id: Uuid,
+        reactions: ReactionCounts,
+    },
+    /// a moderator hid or deleted the post or an edit of it was held, pages
+    /// showing it drop it
+    Retracted { post_id: Uuid },
+    /// only goes to the sessions of `notification.user_id`
+    Notification {
+        notification: NotificationView,
+        unread: i64,
+    },
 }
 
-impl Default for PostsBrokerConfig {
-    fn default() -> Self {
-        Self { n_workers: 1 }
+impl PostsEvent {
+    /// The user an event is addressed to, `None` for broadcasts.
+    fn recipient(&self) -> Option<i32> {
+        match self {
+            PostsEvent::Notification { notification, .. } => Some(notification.user_id),
+            _ => None,
+        }
     }
 }
 
+/// Which of the events coming through the queue a subscriber receives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Feed {
+    /// every new top-level post
+    Global,
+    /// new replies anywhere in the thread rooted at this post
+    Thread(Uuid),
+    /// new top-level posts by the users `user_id` follows
+    Following {
+        user_id: i32,
+        followees: HashSet<i32>,
+    },
+    /// nothing but the events addressed to the subscriber
+    Personal,
+}
+
+impl Feed {
+    fn wants(&self, event: &PostsEvent) -> bool {
+        match (self, event) {
+            (Feed::Global, PostsEvent::Created { post, .. }) => post.parent_id.is_none(),
+            (Feed::Thread(root), PostsEvent::Created { post, .. }) => post.root_id == Some(*root),
+            (Feed::Following { followees, .. }, PostsEvent::Created { post, .. }) => {
+                post.parent_id.is_none() && followees.contains(&post.user_id)
+            }
+            (Feed::Personal, _) => false,
+            // the post may be shown anywhere, let the page sort it out
+            (_, PostsEvent::Reactions { .. } | PostsEvent::Retracted { .. }) => true,
+            (_, PostsEvent::Notification { .. }) => false,
+        }
+    }
+}
+
+/// Hands an event to the posts queue, from where the broker fans it out to
+/// the WebSocket subscribers.
+pub async fn publish(q_pool: &deadpool_lapin::Pool, event: &PostsEvent) -> Result<(), Error> {
+    let channel = q_pool
+        .get()
+        .await
+        .inspect_err(ert!())?
+        .create_channel()
+        .await
+        .inspect_err(ert!())?;
+    channel
+        .basic_publish(
+            "",
+            "posts",
+            BasicPublishOptions {
+                mandatory: true,
+                ..Default::default()
+            },
+            serde_json::to_vec(event).inspect_err(ert!())?.as_slice(),
+            BasicProperties::default().with_content_type("application/json".into()),
+        )
+        .await
+        .inspect_err(ert!())?
+        .await
+        .inspect_err(ert!())?;
+    Ok(())
+}
+
 // #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Subscription {
     pub id: uuid::Uuid,
-    pub rx: tokio::sync::mpsc::Receiver<Arc<serde_json::Value>>,
+    pub rx: tokio::sync::mpsc::Receiver<Arc<PostsEvent>>,
 }
 
 impl Debug for Subscription {
@@ -65,50 +157,139 @@ impl PartialOrd for Subscriber {
 
 struct Subscriber {
     id: uuid::Uuid,
-    tx: tokio::sync::mpsc::Sender<Arc<serde_json::Value>>,
+    feed: Feed,
+    /// the user the session belongs to, if known
+    user_id: Option<i32>,
+    tx: tokio::sync::mpsc::Sender<Arc<PostsEvent>>,
 }
 
 impl Debug for Subscriber {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct(type_name::<Self>())
             .field("id", &self.id)
+            .field("feed", &self.feed)
+            .field("user_id", &self.user_id)
             .finish()
     }
 }
 
 pub struct PostsSubscriptionManager {
     subscriptions: dashmap::DashMap<Uuid, Subscriber>,
+    /// subscription ids per user, for addressed events
+    sessions: dashmap::DashMap<i32, HashSet<Uuid>>,
+    channel_capacity: usize,
+    send_timeout: Duration,
 }
 
 impl Debug for PostsSubscriptionManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PostsSubscriptionManager")
             .field("subscriptions_count", &self.subscriptions.len())
+            .field("users_count", &self.sessions.len())
             .finish()
     }
 }
 
 impl PostsSubscriptionManager {
-    pub fn new() -> Self {
+    pub fn new(cfg: &PostsBrokerCfg) -> Self {
         Self {
             subscriptions: dashmap::DashMap::new(),
+            sessions: dashmap::DashMap::new(),
+            channel_capacity: cfg.channel_capacity,
+            send_timeout: Duration::from_secs(cfg.send_timeout_secs),
         }
     }
 
     #[instrument]
-    pub fn subscribe(&self) -> Subscription {
-        let (tx, rx) = tokio::sync::mpsc::channel(24);
+    pub fn subscribe(&self, feed: Feed, user_id: Option<i32>) -> Subscription {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.channel_capacity);
         let id = uuid::Uuid::now_v7();
-        let sub = Subscriber { id, tx };
-        info!(action = "subscribe", id = %sub.id);
+        let sub = Subscriber {
+            id,
+            feed,
+            user_id,
+            tx,
+        };
+        info!(action = "subscribe", id = %sub.id, feed = ?sub.feed, user_id = ?sub.user_id);
         self.subscriptions.insert(id, sub);
+        if let Some(user_id) = user_id {
+            self.sessions.entry(user_id).or_default().insert(id);
+        }
 
         Subscription { id, rx }
     }
 
+    /// Delivers to every subscriber whose feed wants the event.
+    async fn broadcast(&self, event: Arc<PostsEvent>) {
+        let txs: Vec<_> = self
+            .subscriptions
+            .iter()
+            .filter(|sub| sub.feed.wants(&event))
+            .map(|sub| (sub.id, sub.tx.clone()))
+            .collect();
+        self.send_all(txs, event).await;
+    }
+
+    /// Delivers to the sessions of `user_id` only, whatever their feed.
+    async fn send_to_user(&self, user_id: i32, event: Arc<PostsEvent>) {
+        let txs: Vec<_> = self
+            .sessions
+            .get(&user_id)
+            .into_iter()
+            .flat_map(|ids| {
+                ids.iter()
+                    .filter_map(|id| {
+                        self.subscriptions
+                            .get(id)
+                            .map(|sub| (sub.id, sub.tx.clone()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        self.send_all(txs, event).await;
+    }
+
+    async fn send_all(
+        &self,
+        txs: Vec<(Uuid, tokio::sync::mpsc::Sender<Arc<PostsEvent>>)>,
+        event: Arc<PostsEvent>,
+    ) {
+        for (sub_id, tx) in txs {
+            info!("sending_post to: {sub_id}");
+            if let Err(e) = tx.send_timeout(event.clone(), self.send_timeout).await {
+                error!(%e, "failed to send post to subscriber");
+            }
+        }
+    }
+
+    /// Swaps the followee set of every `Feed::Following` subscription of
+    /// `user_id`, so follows take effect without reconnecting.
+    #[instrument(skip(followees))]
+    pub fn update_following(&self, user_id: i32, followees: HashSet<i32>) {
+        for mut sub in self.subscriptions.iter_mut() {
+            let id = sub.id;
+            if let Feed::Following {
+                user_id: subscriber,
+                followees: current,
+            } = &mut sub.feed
+                && *subscriber == user_id
+            {
+                info!(action = "update_following", %id);
+                *current = followees.clone();
+            }
+        }
+    }
+
     #[instrument]
     pub fn unsubscribe(&self, s: &Uuid) -> Option<Uuid> {
-        self.subscriptions.remove(s).map(|(id, _)| id)
+        let (id, sub) = self.subscriptions.remove(s)?;
+        if let Some(user_id) = sub.user_id {
+            self.sessions.remove_if_mut(&user_id, |_, ids| {
+                ids.remove(&id);
+                ids.is_empty()
+            });
+        }
+        Some(id)
     }
 }
 
@@ -116,17 +297,20 @@ pub struct PostsBroker {
     span: Option<Span>,
     pub posts_subscription_mgr: Arc<PostsSubscriptionManager>,
     q_pool: deadpool_lapin::Pool,
+    n_workers: usize,
 }
 
 impl PostsBroker {
     pub fn new(
         posts_subscription_mgr: Arc<PostsSubscriptionManager>,
         q_pool: deadpool_lapin::Pool,
+        cfg: &PostsBrokerCfg,
     ) -> Self {
         Self {
             span: None,
             posts_subscription_mgr,
             q_pool,
+            n_workers: cfg.n_workers,
         }
     }
 
@@ -193,14 +377,9 @@ impl PostsBroker {
                 Some(delivery)
             })
             .filter_map(|delivery| async move {
-                let v = std::str::from_utf8(&delivery.data)
-                    .inspect_err(ert!())
+                let v = serde_json::from_slice::<PostsEvent>(&delivery.data)
                     .map_err(Error::from)
-                    .and_then(|d| {
-                        serde_json::Value::from_str(d)
-                            .map_err(Error::from)
-                            .inspect_err(ert!())
-                    });
+                    .inspect_err(ert!());
                 info!("deserialized");
 
                 if let Err(e) = &v {
@@ -209,13 +388,11 @@ impl PostsBroker {
                 v.ok()
             })
             // TODO: maybe manage manually instead
-            .for_each_concurrent(5, |v| async move {
+            .for_each_concurrent(self.n_workers, |v| async move {
                 let v = Arc::new(v); // ensures no copy
-                for sub in posts_subscriber_mgr.subscriptions.iter() {
-                    info!("sending_post to: {sub_id}", sub_id = sub.id);
-                    if let Err(e) = sub.tx.send_timeout(v.clone(), Duration::from_secs(5)).await {
-                        error!(%e, "failed to send post to subscriber");
-                    }
+                match v.recipient() {
+                    Some(user_id) => posts_subscriber_mgr.send_to_user(user_id, v).await,
+                    None => posts_subscriber_mgr.broadcast(v).await,
                 }
             })
             .await;
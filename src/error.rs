@@ -1,33 +1,217 @@
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::time::Duration;
 
-use axum::response::Html;
+use axum::extract::multipart::{MultipartError, MultipartRejection};
+use axum::extract::rejection::{FormRejection, JsonRejection, QueryRejection};
+use axum::http::header;
 use axum::{http::StatusCode, response::IntoResponse};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use tracing::{error, warn};
 
-pub struct AppError {
-    pub inner: anyhow::Error,
+use crate::i18n::Message;
+
+/// What went wrong handling a request. The `Message`s are public, shown to
+/// the user in their language; the `anyhow::Error`s are only logged.
+pub enum AppError {
+    NotFound(Message),
+    /// the request is understood but its content is not acceptable
+    Validation(Message),
+    /// the request clashes with what is stored, e.g. a taken email
+    Conflict(Message),
+    /// the request needs a signed in user and there is none
+    Unauthorized(Message),
+    Forbidden(Message),
+    RateLimited {
+        retry_after: Duration,
+    },
+    /// the database or the broker is down or too busy
+    Unavailable(anyhow::Error),
+    Internal(anyhow::Error),
+}
+
+impl AppError {
+    pub fn not_found(msg: impl Into<Message>) -> Self {
+        Self::NotFound(msg.into())
+    }
+
+    pub fn validation(msg: impl Into<Message>) -> Self {
+        Self::Validation(msg.into())
+    }
+
+    pub fn conflict(msg: impl Into<Message>) -> Self {
+        Self::Conflict(msg.into())
+    }
+
+    pub fn unauthorized(msg: impl Into<Message>) -> Self {
+        Self::Unauthorized(msg.into())
+    }
+
+    pub fn forbidden(msg: impl Into<Message>) -> Self {
+        Self::Forbidden(msg.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// What the user gets to see, in the locale of the request. Never
+    /// anything from the error chain of `Unavailable` and `Internal`.
+    pub fn public_message(&self) -> String {
+        match self {
+            Self::NotFound(msg)
+            | Self::Validation(msg)
+            | Self::Conflict(msg)
+            | Self::Unauthorized(msg)
+            | Self::Forbidden(msg) => msg.localize(),
+            Self::RateLimited { retry_after } => Message::new("error-rate-limited")
+                .arg("seconds", retry_after_secs(*retry_after))
+                .localize(),
+            Self::Unavailable(_) => Message::new("error-unavailable").localize(),
+            Self::Internal(_) => Message::new("error-internal").localize(),
+        }
+    }
+
+    /// Picks the variant for an error bubbling up from a service or an
+    /// extractor by looking through its chain.
+    fn classify(err: anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(e) = cause.downcast_ref::<DieselError>() {
+                match e {
+                    DieselError::NotFound => return Self::not_found("error-not-found"),
+                    DieselError::DatabaseError(kind, info) => match kind {
+                        DatabaseErrorKind::UniqueViolation => {
+                            return Self::conflict(unique_violation_message(
+                                info.constraint_name(),
+                            ));
+                        }
+                        DatabaseErrorKind::ForeignKeyViolation => {
+                            return Self::validation("error-missing-reference");
+                        }
+                        DatabaseErrorKind::CheckViolation | DatabaseErrorKind::NotNullViolation => {
+                            return Self::validation("error-invalid-value");
+                        }
+                        DatabaseErrorKind::ClosedConnection => return Self::Unavailable(err),
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+            if cause
+                .downcast_ref::<diesel_async::pooled_connection::deadpool::PoolError>()
+                .is_some()
+                || cause.downcast_ref::<deadpool_lapin::PoolError>().is_some()
+            {
+                // timeouts, and the database or broker refusing connections
+                return Self::Unavailable(err);
+            }
+            if let Some(e) = cause.downcast_ref::<FormRejection>() {
+                return Self::validation(Message::text(e.body_text()));
+            }
+            if let Some(e) = cause.downcast_ref::<QueryRejection>() {
+                return Self::validation(Message::text(e.body_text()));
+            }
+            if let Some(e) = cause.downcast_ref::<JsonRejection>() {
+                return Self::validation(Message::text(e.body_text()));
+            }
+            if let Some(e) = cause.downcast_ref::<MultipartRejection>() {
+                return Self::validation(Message::text(e.body_text()));
+            }
+            if let Some(e) = cause.downcast_ref::<MultipartError>() {
+                return Self::validation(Message::text(e.body_text()));
+            }
+        }
+        Self::Internal(err)
+    }
+}
+
+fn unique_violation_message(constraint: Option<&str>) -> Message {
+    Message::new(match constraint {
+        Some("users_email_key") => "error-email-taken",
+        Some("users_handle_key") => "error-handle-taken",
+        _ => "error-already-exists",
+    })
+}
+
+// rounded up, retrying a bit early would only be limited again
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    (retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)).max(1)
+}
+
+/// The part of an error the client may see. Error responses carry it as an
+/// extension for `middleware::errors` to render as a page, an htmx fragment
+/// or `application/problem+json`, whichever the client asked for.
+#[derive(Debug, Clone)]
+pub struct PublicError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl IntoResponse for PublicError {
+    fn into_response(self) -> axum::response::Response {
+        // the plain text body is only seen without `middleware::errors`
+        let mut res = (self.status, self.message.clone()).into_response();
+        res.extensions_mut().insert(self);
+        res
+    }
 }
 
 // Tell axum how to convert `AppError` into a response.
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Html(format!("Something went wrong: {}", self.inner)),
-        )
-            .into_response()
+        match &self {
+            Self::Internal(e) => error!(error = ?e, "request failed"),
+            Self::Unavailable(e) => warn!(error = ?e, "dependency unavailable"),
+            _ => {}
+        }
+
+        let public = PublicError {
+            status: self.status(),
+            message: self.public_message(),
+        };
+        match self {
+            Self::RateLimited { retry_after } => (
+                [(
+                    header::RETRY_AFTER,
+                    retry_after_secs(retry_after).to_string(),
+                )],
+                public,
+            )
+                .into_response(),
+            _ => public.into_response(),
+        }
     }
 }
 
 impl Display for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(&self.inner, f)
+        match self {
+            Self::Unavailable(e) | Self::Internal(e) => std::fmt::Display::fmt(e, f),
+            _ => f.write_str(&self.public_message()),
+        }
     }
 }
 
 impl Debug for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Debug::fmt(&self.inner, f)
+        match self {
+            Self::Unavailable(e) => f.debug_tuple("Unavailable").field(e).finish(),
+            Self::Internal(e) => f.debug_tuple("Internal").field(e).finish(),
+            _ => f
+                .debug_tuple("AppError")
+                .field(&self.status())
+                .field(&self.public_message())
+                .finish(),
+        }
     }
 }
 
@@ -38,6 +222,144 @@ where
     E: Into<anyhow::Error>,
 {
     fn from(err: E) -> Self {
-        Self { inner: err.into() }
+        Self::classify(err.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Query;
+    use diesel::result::DatabaseErrorInformation;
+
+    use super::*;
+    use crate::models::ActingUser;
+
+    struct Info(Option<&'static str>);
+
+    impl DatabaseErrorInformation for Info {
+        fn message(&self) -> &str {
+            "duplicate key value violates unique constraint"
+        }
+        fn details(&self) -> Option<&str> {
+            Some("Key (email)=(a@x.io) already exists.")
+        }
+        fn hint(&self) -> Option<&str> {
+            None
+        }
+        fn table_name(&self) -> Option<&str> {
+            Some("users")
+        }
+        fn column_name(&self) -> Option<&str> {
+            None
+        }
+        fn constraint_name(&self) -> Option<&str> {
+            self.0
+        }
+        fn statement_position(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    fn db_error(kind: DatabaseErrorKind, constraint: Option<&'static str>) -> AppError {
+        let e = DieselError::DatabaseError(kind, Box::new(Info(constraint)));
+        AppError::from(anyhow::Error::from(e).context("creating user"))
+    }
+
+    #[test]
+    fn unique_violations_are_conflicts_named_by_constraint() {
+        let taken = |constraint| match db_error(DatabaseErrorKind::UniqueViolation, constraint) {
+            AppError::Conflict(Message::Id { id, .. }) => id,
+            e => panic!("not a conflict: {e:?}"),
+        };
+        assert_eq!(taken(Some("users_email_key")), "error-email-taken");
+        assert_eq!(taken(Some("users_handle_key")), "error-handle-taken");
+        assert_eq!(taken(Some("follows_pkey")), "error-already-exists");
+        assert_eq!(taken(None), "error-already-exists");
+
+        let e = db_error(DatabaseErrorKind::UniqueViolation, None);
+        assert_eq!(e.status(), StatusCode::CONFLICT);
+        assert!(!e.public_message().contains("a@x.io"));
+    }
+
+    #[test]
+    fn foreign_key_violations_are_invalid_requests() {
+        let e = db_error(
+            DatabaseErrorKind::ForeignKeyViolation,
+            Some("posts_user_id_fkey"),
+        );
+        assert_eq!(e.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(matches!(
+            e,
+            AppError::Validation(Message::Id {
+                id: "error-missing-reference",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn pool_timeouts_are_unavailable() {
+        use deadpool::managed::{PoolError, TimeoutType};
+
+        let e = PoolError::<diesel_async::pooled_connection::PoolError>::Timeout(TimeoutType::Wait);
+        let e = AppError::from(anyhow::Error::from(e).context("loading feed"));
+        assert_eq!(e.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(matches!(e, AppError::Unavailable(_)));
+    }
+
+    #[test]
+    fn extractor_rejections_are_invalid_requests() {
+        let rejection = Query::<ActingUser>::try_from_uri(&"/?user_id=x".parse().unwrap())
+            .err()
+            .unwrap();
+        let e = AppError::from(rejection);
+        assert_eq!(e.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(e.public_message().contains("user_id"), "{e:?}");
+    }
+
+    #[test]
+    fn internal_errors_never_show_their_chain() {
+        let e = AppError::from(
+            anyhow::anyhow!("connecting with password hunter2").context("loading feed"),
+        );
+        assert_eq!(e.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!e.public_message().contains("hunter2"));
+        assert!(!e.public_message().contains("feed"));
+
+        let res = e.into_response();
+        let public = res.extensions().get::<PublicError>().unwrap();
+        assert_eq!(public.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!public.message.contains("hunter2"));
+        assert!(!public.message.contains("feed"));
+    }
+
+    #[test]
+    fn unauthorized_is_a_401_with_its_message() {
+        let e = AppError::unauthorized(Message::text("who are you?"));
+        assert_eq!(e.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(e.public_message(), "who are you?");
+
+        let res = e.into_response();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let public = res.extensions().get::<PublicError>().unwrap();
+        assert_eq!(public.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(public.message, "who are you?");
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        let secs = |ms| retry_after_secs(Duration::from_millis(ms));
+        assert_eq!(secs(0), 1);
+        assert_eq!(secs(1), 1);
+        assert_eq!(secs(1000), 1);
+        assert_eq!(secs(1001), 2);
+        assert_eq!(secs(59_999), 60);
+
+        let res = AppError::RateLimited {
+            retry_after: Duration::from_millis(2500),
+        }
+        .into_response();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[header::RETRY_AFTER], "3");
     }
 }
@@ -0,0 +1,369 @@
+//! Translations of everything shown to users, from the Fluent catalogs in
+//! `src/locales/<locale>/*.ftl`. They are compiled into the binary and loaded
+//! once at startup by `init`.
+//!
+//! Each request is handled in the locale `middleware::locale` negotiated for
+//! it. `Message::localize`, the `t()` Tera function and the `relative_time`
+//! filter use it without it being passed around, like the CSRF token.
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::OnceLock;
+
+use anyhow::anyhow;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{NegotiationStrategy, negotiate_languages};
+use rust_embed::RustEmbed;
+use tracing::warn;
+use unic_langid::LanguageIdentifier;
+
+/// Used when nothing the client accepts is translated, and for messages
+/// missing from the other catalogs.
+pub const DEFAULT_LOCALE: &str = "en";
+
+#[derive(RustEmbed)]
+#[folder = "src/locales/"]
+struct Locales;
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+tokio::task_local! {
+    static LOCALE: LanguageIdentifier;
+}
+
+/// Loads the catalogs, failing on any syntax error or duplicate message.
+pub fn init() -> anyhow::Result<()> {
+    let catalog = Catalog::load()?;
+    let _ = CATALOG.set(catalog);
+    Ok(())
+}
+
+/// Runs `fut` with `locale` as the locale of everything it renders.
+pub async fn scope<F: Future>(locale: LanguageIdentifier, fut: F) -> F::Output {
+    LOCALE.scope(locale, fut).await
+}
+
+/// The locale of the request being handled, `DEFAULT_LOCALE` outside of
+/// requests.
+pub fn current() -> LanguageIdentifier {
+    LOCALE
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| default_locale())
+}
+
+fn default_locale() -> LanguageIdentifier {
+    DEFAULT_LOCALE.parse().unwrap_or_default()
+}
+
+/// The best translated locale for `requested`, most wanted first.
+pub fn negotiate(requested: &[LanguageIdentifier]) -> LanguageIdentifier {
+    let Some(catalog) = CATALOG.get() else {
+        return default_locale();
+    };
+    let available: Vec<_> = catalog.bundles.keys().cloned().collect();
+    let default = default_locale();
+    negotiate_languages(
+        requested,
+        &available,
+        Some(&default),
+        NegotiationStrategy::Lookup,
+    )
+    .first()
+    .map_or(default.clone(), |l| (*l).clone())
+}
+
+/// Every translated locale with its name in its own language, for a picker.
+pub fn available() -> Vec<(LanguageIdentifier, String)> {
+    let Some(catalog) = CATALOG.get() else {
+        return vec![];
+    };
+    catalog
+        .bundles
+        .keys()
+        .map(|l| (l.clone(), catalog.format(l, "language-name", None)))
+        .collect()
+}
+
+/// Whether `locale` has a catalog of its own.
+pub fn is_available(locale: &LanguageIdentifier) -> bool {
+    CATALOG
+        .get()
+        .is_some_and(|c| c.bundles.contains_key(locale))
+}
+
+/// Message `id` with `args` in the current locale.
+pub fn format(id: &str, args: Option<&FluentArgs>) -> String {
+    match CATALOG.get() {
+        Some(catalog) => catalog.format(&current(), id, args),
+        None => id.to_owned(),
+    }
+}
+
+struct Catalog {
+    bundles: BTreeMap<LanguageIdentifier, FluentBundle<FluentResource>>,
+}
+
+impl Catalog {
+    fn load() -> anyhow::Result<Self> {
+        let files = Locales::iter()
+            .map(|path| {
+                let file = Locales::get(&path).ok_or_else(|| anyhow!("`{path}` went missing"))?;
+                Ok((
+                    path.into_owned(),
+                    String::from_utf8(file.data.into_owned())?,
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Self::from_sources(files)
+    }
+
+    /// The catalog of `(path, source)` pairs, the path being
+    /// `<locale>/<name>.ftl`.
+    fn from_sources(files: impl IntoIterator<Item = (String, String)>) -> anyhow::Result<Self> {
+        let mut bundles = BTreeMap::new();
+        let mut problems = vec![];
+        for (path, source) in files {
+            let Some((locale, _)) = path.split_once('/') else {
+                continue;
+            };
+            let locale: LanguageIdentifier = locale
+                .parse()
+                .map_err(|e| anyhow!("`{path}` is not in a locale directory: {e}"))?;
+
+            let resource = FluentResource::try_new(source).unwrap_or_else(|(resource, errors)| {
+                problems.extend(errors.iter().map(|e| format!("{path}: {e}")));
+                resource
+            });
+            let bundle = bundles.entry(locale.clone()).or_insert_with(|| {
+                let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+                // the Unicode isolation marks around arguments would end up
+                // in attributes and titles; none of the locales is written
+                // right to left
+                bundle.set_use_isolating(false);
+                bundle
+            });
+            if let Err(errors) = bundle.add_resource(resource) {
+                problems.extend(errors.iter().map(|e| format!("{path}: {e}")));
+            }
+        }
+
+        if !bundles.contains_key(&default_locale()) {
+            problems.push(format!(
+                "no catalog for the default locale `{DEFAULT_LOCALE}`"
+            ));
+        }
+        if !problems.is_empty() {
+            return Err(anyhow!("invalid translations:\n{}", problems.join("\n")));
+        }
+        Ok(Self { bundles })
+    }
+
+    // falls back to the default locale, then to the id itself
+    fn format(&self, locale: &LanguageIdentifier, id: &str, args: Option<&FluentArgs>) -> String {
+        let default = default_locale();
+        for locale in [locale, &default] {
+            let Some(bundle) = self.bundles.get(locale) else {
+                continue;
+            };
+            let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) else {
+                continue;
+            };
+            let mut errors = vec![];
+            let text = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                warn!(id, %locale, ?errors, "could not format message");
+            }
+            return text.into_owned();
+        }
+        warn!(id, %locale, "no such message");
+        id.to_owned()
+    }
+}
+
+/// Text for the user, turned into their language by `localize`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// a message of the catalogs with its arguments
+    Id {
+        id: &'static str,
+        args: Vec<(&'static str, Arg)>,
+    },
+    /// text from elsewhere shown as it is, e.g. the rejections of axum's
+    /// extractors
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arg {
+    Number(i64),
+    Text(String),
+}
+
+impl Message {
+    pub fn new(id: &'static str) -> Self {
+        Self::Id { id, args: vec![] }
+    }
+
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn arg(mut self, name: &'static str, value: impl Into<Arg>) -> Self {
+        if let Self::Id { args, .. } = &mut self {
+            args.push((name, value.into()));
+        }
+        self
+    }
+
+    /// The message in the locale of the current request.
+    pub fn localize(&self) -> String {
+        match self {
+            Self::Id { id, args } => {
+                let mut fluent_args = FluentArgs::new();
+                for (name, value) in args {
+                    let value = match value {
+                        Arg::Number(n) => FluentValue::from(*n),
+                        Arg::Text(s) => FluentValue::String(Cow::Borrowed(s.as_str())),
+                    };
+                    fluent_args.set(*name, value);
+                }
+                format(id, Some(&fluent_args))
+            }
+            Self::Text(text) => text.clone(),
+        }
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(id: &'static str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<i64> for Arg {
+    fn from(n: i64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<usize> for Arg {
+    fn from(n: usize) -> Self {
+        Self::Number(n.try_into().unwrap_or(i64::MAX))
+    }
+}
+
+impl From<u32> for Arg {
+    fn from(n: u32) -> Self {
+        Self::Number(n.into())
+    }
+}
+
+impl From<u64> for Arg {
+    fn from(n: u64) -> Self {
+        Self::Number(n.try_into().unwrap_or(i64::MAX))
+    }
+}
+
+impl From<String> for Arg {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<&str> for Arg {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use fluent_syntax::ast::Entry;
+
+    use super::*;
+
+    fn catalog(files: &[(&str, &str)]) -> anyhow::Result<Catalog> {
+        Catalog::from_sources(files.iter().map(|(p, s)| (p.to_string(), s.to_string())))
+    }
+
+    fn ids(locale: &str) -> BTreeSet<String> {
+        Locales::iter()
+            .filter(|path| path.starts_with(&format!("{locale}/")))
+            .flat_map(|path| {
+                let file = Locales::get(&path).unwrap();
+                let source = String::from_utf8(file.data.into_owned()).unwrap();
+                let resource = fluent_syntax::parser::parse(source).unwrap();
+                resource
+                    .body
+                    .into_iter()
+                    .filter_map(|entry| match entry {
+                        Entry::Message(m) => Some(m.id.name),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_locale_has_the_messages_of_the_default_one() {
+        let default = ids(DEFAULT_LOCALE);
+        let locales: BTreeSet<_> = Locales::iter()
+            .filter_map(|path| Some(path.split_once('/')?.0.to_owned()))
+            .collect();
+        assert!(locales.len() > 1);
+        for locale in locales {
+            let ids = ids(&locale);
+            let missing: Vec<_> = default.difference(&ids).collect();
+            let extra: Vec<_> = ids.difference(&default).collect();
+            assert!(missing.is_empty(), "missing in `{locale}`: {missing:?}");
+            assert!(extra.is_empty(), "only in `{locale}`: {extra:?}");
+        }
+    }
+
+    #[test]
+    fn format_falls_back_to_the_default_locale_then_the_id() {
+        let catalog = catalog(&[
+            ("en/main.ftl", "greeting = Hello\nfarewell = Bye"),
+            ("de/main.ftl", "greeting = Hallo"),
+        ])
+        .unwrap();
+        let de = "de".parse().unwrap();
+        assert_eq!(catalog.format(&de, "greeting", None), "Hallo");
+        assert_eq!(catalog.format(&de, "farewell", None), "Bye");
+        assert_eq!(catalog.format(&de, "missing", None), "missing");
+    }
+
+    #[test]
+    fn load_reports_broken_catalogs() {
+        assert!(catalog(&[("de/main.ftl", "greeting = Hallo")]).is_err());
+        assert!(catalog(&[("en/main.ftl", "greeting = Hello\ngreeting = Hi")]).is_err());
+        assert!(catalog(&[("en/main.ftl", "greeting Hello")]).is_err());
+    }
+
+    #[test]
+    fn negotiate_falls_back_to_the_default_locale() {
+        init().unwrap();
+        let negotiate = |requested: &[&str]| {
+            let requested: Vec<_> = requested.iter().map(|l| l.parse().unwrap()).collect();
+            negotiate(&requested).to_string()
+        };
+        assert_eq!(negotiate(&["fr", "de"]), "de");
+        assert_eq!(negotiate(&["de", "en"]), "de");
+        assert_eq!(negotiate(&["fr"]), "en");
+        assert_eq!(negotiate(&[]), "en");
+    }
+
+    #[tokio::test]
+    async fn localize_passes_the_arguments() {
+        init().unwrap();
+        let message = Message::new("error-too-many-attachments").arg("max", 4_u32);
+        assert_eq!(message.localize(), "at most 4 attachments per post");
+        let de = scope("de".parse().unwrap(), async { message.localize() }).await;
+        assert_eq!(de, "höchstens 4 Anhänge pro Beitrag");
+        assert_eq!(Message::text("as it is").localize(), "as it is");
+    }
+}
@@ -1,2 +1,34 @@
 import htmx from "htmx.org"
 import "htmx-ext-ws"
+
+// errors come back as a fragment retargeted to `#errors`, so swap those too
+htmx.config.responseHandling = [
+  { code: "204", swap: false },
+  { code: "[23]..", swap: true },
+  { code: "[45]..", swap: true, error: true },
+]
+
+// a `datetime-local` input has no time zone, so the one marked `data-zoned`
+// keeps the hidden input it names at the same time with the browser's offset
+const zonedField = (input) =>
+  input.form?.querySelector(`input[type=hidden][name="${input.dataset.zoned}"]`)
+
+document.addEventListener("change", (e) => {
+  const input = e.target.closest?.("input[data-zoned]")
+  const field = input && zonedField(input)
+  if (field) {
+    field.value = input.value ? new Date(input.value).toISOString() : ""
+  }
+})
+
+// and shows a time that is already set in local time
+htmx.onLoad((root) => {
+  root.querySelectorAll("input[data-zoned]").forEach((input) => {
+    const value = zonedField(input)?.value
+    if (value) {
+      const at = new Date(value)
+      const local = new Date(at.getTime() - at.getTimezoneOffset() * 60_000)
+      input.value = local.toISOString().slice(0, 16)
+    }
+  })
+})
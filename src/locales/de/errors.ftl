@@ -0,0 +1,72 @@
+error-title = { $status ->
+    [400] Ungültige Anfrage
+    [401] Nicht angemeldet
+    [403] Verboten
+    [404] Nicht gefunden
+    [405] Methode nicht erlaubt
+    [406] Nicht annehmbar
+    [408] Zeitüberschreitung der Anfrage
+    [409] Konflikt
+    [411] Länge erforderlich
+    [413] Inhalt zu groß
+    [414] URI zu lang
+    [415] Nicht unterstützter Medientyp
+    [422] Nicht verarbeitbarer Inhalt
+    [429] Zu viele Anfragen
+    [500] Interner Serverfehler
+    [502] Fehlerhaftes Gateway
+    [503] Dienst nicht verfügbar
+    [504] Gateway-Zeitüberschreitung
+   *[other] Fehler
+}
+error-reference = Referenz:
+error-status-reference = { $status } { $title }, Referenz
+
+error-rate-limited = { $seconds ->
+    [one] Zu viele Anfragen, versuche es in { $seconds } Sekunde erneut.
+   *[other] Zu viele Anfragen, versuche es in { $seconds } Sekunden erneut.
+}
+error-unavailable = Der Dienst ist ausgelastet, versuche es gleich noch einmal.
+error-internal = Etwas ist schiefgelaufen.
+error-csrf = CSRF-Token fehlt oder ist veraltet, lade die Seite neu und versuche es erneut.
+error-signed-out = lege zuerst in diesem Browser einen Benutzer an, damit meldest du dich als dieser an
+error-locale-unknown = diese Sprache ist nicht verfügbar
+
+error-not-found = nicht gefunden
+error-missing-reference = verweist auf etwas, das es nicht gibt
+error-invalid-value = ein Wert fehlt oder liegt außerhalb des gültigen Bereichs
+error-already-exists = das gibt es schon
+
+error-user-not-found = Nutzer nicht gefunden
+error-email-invalid = ungültige E-Mail-Adresse
+error-email-taken = diese E-Mail-Adresse ist schon vergeben
+error-handle-taken = dieser Name ist schon vergeben
+error-follow-self = niemand kann sich selbst folgen
+error-avatar-missing = wähle ein Bild für den Avatar
+error-avatar-size-not-found = keinen Avatar in dieser Größe gefunden
+error-blob-not-found = Datei nicht gefunden
+
+error-post-not-found = Beitrag nicht gefunden
+error-draft-not-found = Entwurf nicht gefunden
+error-revision-not-found = Version nicht gefunden
+error-edit-not-author = nur der Autor kann einen Beitrag bearbeiten
+error-publish-at-past = publish_at muss in der Zukunft liegen
+error-publish-at-missing = zum Planen ist publish_at nötig
+error-too-many-attachments = höchstens { $max } Anhänge pro Beitrag
+error-image-too-large = Bilder dürfen höchstens { $mib } MiB groß sein
+error-image-unsupported = Bilder müssen PNG, JPEG oder WebP sein
+error-image-invalid = kein gültiges Bild: { $error }
+error-post-rejected = Dein Beitrag wurde nicht veröffentlicht: { $reason }.
+
+error-moderators-only = nur für Moderatoren
+error-restore-not-moderator = nur Moderatoren können Versionen wiederherstellen
+error-held-post-not-found = zurückgehaltenen Beitrag nicht gefunden
+error-reason-required = gib einen Grund für die Meldung an
+error-reason-too-long = gib einen Grund mit höchstens 500 Zeichen an
+error-suspended = gesperrt bis { $until }
+error-suspension-hours = sperre für 1 bis { $max } Stunden
+
+reject-too-long = Beiträge dürfen höchstens { $max } Zeichen lang sein
+reject-blocked-word = enthält ein nicht erlaubtes Wort
+reject-duplicate = genau das hast du gerade schon gepostet
+reject-quota = du kannst { $max } Beiträge pro { $minutes } Minuten verfassen, versuche es später erneut
@@ -0,0 +1,59 @@
+language-name = Deutsch
+language = Sprache
+
+site-name = Große Nutzerseite!
+acting-as = Handeln als Nutzer-ID
+user-id = Nutzer-ID
+back-to-start = Zurück zur Startseite
+loading-more = Mehr wird geladen …
+
+title-users = Nutzer
+title-home-feed = Startfeed
+title-thread = Diskussion
+title-drafts = Entwürfe
+
+time-just-now = gerade eben
+time-ago = { $unit ->
+    [minute] { $n ->
+        [one] vor { $n } Minute
+       *[other] vor { $n } Minuten
+    }
+    [hour] { $n ->
+        [one] vor { $n } Stunde
+       *[other] vor { $n } Stunden
+    }
+    [day] { $n ->
+        [one] vor { $n } Tag
+       *[other] vor { $n } Tagen
+    }
+    [month] { $n ->
+        [one] vor { $n } Monat
+       *[other] vor { $n } Monaten
+    }
+   *[year] { $n ->
+        [one] vor { $n } Jahr
+       *[other] vor { $n } Jahren
+    }
+}
+time-in = { $unit ->
+    [minute] { $n ->
+        [one] in { $n } Minute
+       *[other] in { $n } Minuten
+    }
+    [hour] { $n ->
+        [one] in { $n } Stunde
+       *[other] in { $n } Stunden
+    }
+    [day] { $n ->
+        [one] in { $n } Tag
+       *[other] in { $n } Tagen
+    }
+    [month] { $n ->
+        [one] in { $n } Monat
+       *[other] in { $n } Monaten
+    }
+   *[year] { $n ->
+        [one] in { $n } Jahr
+       *[other] in { $n } Jahren
+    }
+}
@@ -0,0 +1,32 @@
+moderation-queue = Moderationswarteschlange
+moderation-reported = Gemeldete Beiträge
+moderation-held = Zur Prüfung zurückgehalten
+moderation-actions = Letzte Aktionen
+moderation-hidden = (ausgeblendet)
+moderation-reporter = Nutzer { $id },
+moderation-held-reason = zurückgehalten: { $reason }
+moderation-reason = Grund (optional)
+moderation-hide = Ausblenden
+moderation-delete = Löschen
+moderation-delete-confirm = Diesen Beitrag und seine Antworten endgültig löschen?
+moderation-delete-held-confirm = Diesen Beitrag endgültig löschen?
+moderation-dismiss = Meldungen verwerfen
+moderation-approve = Freigeben
+moderation-suspend = Autor sperren (Stunden)
+moderation-nothing-reported = Nichts gemeldet.
+moderation-nothing-held = Nichts zurückgehalten.
+moderation-no-actions = Noch keine Aktionen.
+report-received = Danke für die Meldung, ein Moderator sieht sie sich an.
+
+moderation-by = Moderator { $id }
+moderation-by-deleted = ein gelöschter Moderator
+moderation-kind = { $kind ->
+    [hide] ausgeblendet
+    [delete] gelöscht
+    [suspend] gesperrt
+    [dismiss] verworfen
+   *[approve] freigegeben
+}
+moderation-action-post = Beitrag { $id }
+moderation-action-user = von Nutzer { $id }
+moderation-action-until = bis { $until }
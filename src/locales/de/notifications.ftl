@@ -0,0 +1,12 @@
+notifications = Benachrichtigungen
+notifications-heading = Benachrichtigungen für Nutzer { $id }
+notifications-mark-all-read = Alle als gelesen markieren
+notifications-none = Noch nichts.
+notification = { $actor } { $kind ->
+    [mention] hat dich in einem Beitrag erwähnt
+    [reply] hat auf deinen Beitrag geantwortet
+    [reaction] hat auf deinen Beitrag reagiert
+   *[follow] folgt dir jetzt
+}
+notification-view = ansehen
+notification-mark-read = Als gelesen markieren
@@ -0,0 +1,61 @@
+post-id = Beitrags-ID: { $id }
+post-author = Nutzer-ID: { $id }
+post-posted = Gepostet
+post-content = Inhalt
+post-images = Bilder
+post-publish-at = Veröffentlichen am
+post-tags = Tags
+post-tags-placeholder = rust, htmx
+post-create = Beitrag erstellen
+post-save = Speichern
+post-save-draft = Entwurf speichern
+post-schedule = Planen
+post-publish-now = Jetzt veröffentlichen
+post-edit = Bearbeiten
+post-edited = (bearbeitet)
+post-edited-at = bearbeitet { $at }
+post-reply = Antworten
+post-report = Melden
+post-report-reason = Grund der Meldung
+post-replies = { $count ->
+    [one] { $count } Antwort
+   *[other] { $count } Antworten
+}
+post-direct-replies = { $count ->
+    [one] { $count } direkte Antwort
+   *[other] { $count } direkte Antworten
+}
+attachment-alt = Anhang { $n }
+reaction-name = { $reaction ->
+    [like] gefällt mir
+    [love] liebe ich
+    [laugh] lustig
+    [wow] wow
+   *[sad] traurig
+}
+
+reply-posted = Antwort gepostet. ID = { $id }
+post-published = Beitrag { $id } veröffentlicht.
+post-scheduled = Beitrag { $id } erscheint
+post-held = Beitrag { $id } wartet auf die Freigabe durch einen Moderator.
+draft-saved = Entwurf { $id } gespeichert.
+
+drafts-mine = Meine Entwürfe
+drafts-draft = Entwurf
+drafts-scheduled = Geplant
+drafts-scheduled-for = für
+drafts-none = Keine Entwürfe.
+
+home-feed-load = Startfeed laden
+feed-empty = Noch nichts hier. Folge jemandem!
+
+search-posts = Beiträge suchen
+search-author = Autor-ID
+search-no-match = Keine Beiträge passen zu „{ $q }“.
+
+revisions-back = zurück zum Beitrag
+revisions-heading = Versionsverlauf
+revisions-edited-by = Bearbeitet von Nutzer { $id }
+revisions-restore = Diese Version wiederherstellen
+revisions-restore-confirm = Die Version vor dieser Änderung wiederherstellen?
+revisions-none = Nie bearbeitet.
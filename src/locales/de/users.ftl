@@ -0,0 +1,22 @@
+user-email = E-Mail
+user-handle = Name
+user-handle-placeholder = für @Erwähnungen
+user-password = Passwort
+user-create = Nutzer anlegen
+user-created = { $email } wurde angelegt. ID = { $id }
+user-summary = Id: { $id } - { $email }
+user-joined = dabei seit
+user-followers = Follower
+user-following = folgt
+
+follow = Folgen
+unfollow = Entfolgen
+follows-heading = Nutzer { $id }: { $direction ->
+    [followers] Follower
+   *[following] folgt
+}
+follows-none = Noch niemand.
+
+avatar-alt = Avatar von Nutzer { $id }
+avatar-upload = Avatar hochladen
+avatar-updated = Avatar aktualisiert.
@@ -0,0 +1,77 @@
+# Error pages and the messages of `AppError`, see `middleware::errors`.
+
+# the reason phrase of an HTTP status
+error-title = { $status ->
+    [400] Bad Request
+    [401] Unauthorized
+    [403] Forbidden
+    [404] Not Found
+    [405] Method Not Allowed
+    [406] Not Acceptable
+    [408] Request Timeout
+    [409] Conflict
+    [411] Length Required
+    [413] Payload Too Large
+    [414] URI Too Long
+    [415] Unsupported Media Type
+    [422] Unprocessable Entity
+    [429] Too Many Requests
+    [500] Internal Server Error
+    [502] Bad Gateway
+    [503] Service Unavailable
+    [504] Gateway Timeout
+   *[other] Error
+}
+error-reference = Reference:
+error-status-reference = { $status } { $title }, reference
+
+error-rate-limited = { $seconds ->
+    [one] Too many requests, try again in { $seconds } second.
+   *[other] Too many requests, try again in { $seconds } seconds.
+}
+error-unavailable = The service is busy, try again in a moment.
+error-internal = Something went wrong.
+error-csrf = Missing or stale CSRF token, reload the page and try again.
+error-signed-out = create a user in this browser first, it signs you in as them
+error-locale-unknown = that language is not available
+
+error-not-found = not found
+error-missing-reference = refers to something that does not exist
+error-invalid-value = a value is missing or out of range
+error-already-exists = that already exists
+
+error-user-not-found = user not found
+error-email-invalid = email invalid
+error-email-taken = that email is already taken
+error-handle-taken = that handle is already taken
+error-follow-self = users cannot follow themselves
+error-avatar-missing = pick an image for the avatar
+error-avatar-size-not-found = no avatar of that size
+error-blob-not-found = blob not found
+
+error-post-not-found = post not found
+error-draft-not-found = draft not found
+error-revision-not-found = revision not found
+error-edit-not-author = only the author can edit a post
+error-publish-at-past = publish_at must be in the future
+error-publish-at-missing = publish_at is required to schedule
+error-too-many-attachments = at most { $max } attachments per post
+error-image-too-large = images can be at most { $mib } MiB
+error-image-unsupported = images must be PNG, JPEG or WebP
+error-image-invalid = not a valid image: { $error }
+# $reason is one of the `reject-` messages
+error-post-rejected = Your post was not published: { $reason }.
+
+error-moderators-only = moderators only
+error-restore-not-moderator = only moderators can restore revisions
+error-held-post-not-found = held post not found
+error-reason-required = give a reason for the report
+error-reason-too-long = give a reason of up to 500 characters
+error-suspended = suspended until { $until }
+error-suspension-hours = suspend for 1 to { $max } hours
+
+# why a content filter rejected a post
+reject-too-long = posts can be at most { $max } characters
+reject-blocked-word = contains a word that is not allowed
+reject-duplicate = you just posted the same thing
+reject-quota = you can make { $max } posts per { $minutes } minutes, try again later
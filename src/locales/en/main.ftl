@@ -0,0 +1,65 @@
+# Shared by every page. Each locale has the same files; a message missing
+# from one falls back to the `en` one.
+
+# in its own language, for the language picker
+language-name = English
+language = Language
+
+site-name = Big user site!
+acting-as = Acting as user ID
+user-id = User ID
+back-to-start = Back to the start page
+loading-more = Loading more...
+
+# page titles, see `HxRequest::render`
+title-users = Users
+title-home-feed = Home feed
+title-thread = Thread
+title-drafts = Drafts
+
+# the `relative_time` filter; $unit is minute, hour, day, month or year
+time-just-now = just now
+time-ago = { $unit ->
+    [minute] { $n ->
+        [one] { $n } minute ago
+       *[other] { $n } minutes ago
+    }
+    [hour] { $n ->
+        [one] { $n } hour ago
+       *[other] { $n } hours ago
+    }
+    [day] { $n ->
+        [one] { $n } day ago
+       *[other] { $n } days ago
+    }
+    [month] { $n ->
+        [one] { $n } month ago
+       *[other] { $n } months ago
+    }
+   *[year] { $n ->
+        [one] { $n } year ago
+       *[other] { $n } years ago
+    }
+}
+time-in = { $unit ->
+    [minute] { $n ->
+        [one] in { $n } minute
+       *[other] in { $n } minutes
+    }
+    [hour] { $n ->
+        [one] in { $n } hour
+       *[other] in { $n } hours
+    }
+    [day] { $n ->
+        [one] in { $n } day
+       *[other] in { $n } days
+    }
+    [month] { $n ->
+        [one] in { $n } month
+       *[other] in { $n } months
+    }
+   *[year] { $n ->
+        [one] in { $n } year
+       *[other] in { $n } years
+    }
+}
@@ -0,0 +1,33 @@
+moderation-queue = Moderation queue
+moderation-reported = Reported posts
+moderation-held = Held for review
+moderation-actions = Recent actions
+moderation-hidden = (hidden)
+# followed by when and why
+moderation-reporter = User { $id },
+moderation-held-reason = held: { $reason }
+moderation-reason = Reason (optional)
+moderation-hide = Hide
+moderation-delete = Delete
+moderation-delete-confirm = Delete this post and its replies for good?
+moderation-delete-held-confirm = Delete this post for good?
+moderation-dismiss = Dismiss reports
+moderation-approve = Approve
+moderation-suspend = Suspend author (hours)
+moderation-nothing-reported = Nothing reported.
+moderation-nothing-held = Nothing held.
+moderation-no-actions = No actions yet.
+report-received = Reported, thanks. A moderator will have a look.
+
+moderation-by = moderator { $id }
+moderation-by-deleted = a deleted moderator
+moderation-kind = { $kind ->
+    [hide] hide
+    [delete] delete
+    [suspend] suspend
+    [dismiss] dismiss
+   *[approve] approve
+}
+moderation-action-post = post { $id }
+moderation-action-user = of user { $id }
+moderation-action-until = until { $until }
@@ -0,0 +1,12 @@
+notifications = Notifications
+notifications-heading = Notifications for user { $id }
+notifications-mark-all-read = Mark all read
+notifications-none = Nothing yet.
+notification = { $actor } { $kind ->
+    [mention] mentioned you in a post
+    [reply] replied to your post
+    [reaction] reacted to your post
+   *[follow] started following you
+}
+notification-view = view
+notification-mark-read = Mark read
@@ -0,0 +1,63 @@
+post-id = Post ID: { $id }
+post-author = User ID: { $id }
+post-posted = Posted
+post-content = Post content
+post-images = Images
+post-publish-at = Publish at
+post-tags = Tags
+post-tags-placeholder = rust, htmx
+post-create = Create post
+post-save = Save
+post-save-draft = Save draft
+post-schedule = Schedule
+post-publish-now = Publish now
+post-edit = Edit
+post-edited = (edited)
+post-edited-at = edited { $at }
+post-reply = Reply
+post-report = Report
+post-report-reason = Report reason
+post-replies = { $count ->
+    [one] { $count } reply
+   *[other] { $count } replies
+}
+post-direct-replies = { $count ->
+    [one] { $count } direct reply
+   *[other] { $count } direct replies
+}
+attachment-alt = attachment { $n }
+reaction-name = { $reaction ->
+    [like] like
+    [love] love
+    [laugh] laugh
+    [wow] wow
+   *[sad] sad
+}
+
+reply-posted = Reply posted. ID = { $id }
+post-published = Published post { $id }.
+# followed by the time it goes out
+post-scheduled = Post { $id } goes out
+post-held = Post { $id } is waiting for a moderator to approve it.
+draft-saved = Saved draft { $id }.
+
+drafts-mine = My drafts
+drafts-draft = Draft
+drafts-scheduled = Scheduled
+# followed by the time it goes out
+drafts-scheduled-for = for
+drafts-none = No drafts.
+
+home-feed-load = Load home feed
+feed-empty = Nothing here yet. Follow someone!
+
+search-posts = Search posts
+search-author = Author ID
+search-no-match = No posts match "{ $q }".
+
+revisions-back = back to the post
+revisions-heading = Edit history
+revisions-edited-by = Edited by user { $id }
+revisions-restore = Restore this version
+revisions-restore-confirm = Restore the version from before this edit?
+revisions-none = Never edited.
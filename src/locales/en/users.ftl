@@ -0,0 +1,23 @@
+user-email = Email
+user-handle = Handle
+user-handle-placeholder = for @mentions
+user-password = Password
+user-create = Create user
+user-created = { $email } has been created. ID = { $id }
+user-summary = Id: { $id } - { $email }
+user-joined = joined
+user-followers = followers
+user-following = following
+
+follow = Follow
+unfollow = Unfollow
+# $direction is followers or following
+follows-heading = User { $id }: { $direction ->
+    [followers] followers
+   *[following] following
+}
+follows-none = Nobody yet.
+
+avatar-alt = avatar of user { $id }
+avatar-upload = Upload avatar
+avatar-updated = Avatar updated.
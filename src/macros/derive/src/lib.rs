@@ -0,0 +1,123 @@
+//! `#[derive(Template)]`, re-exported by the `macros` crate, which documents
+//! it. A crate of its own because `proc-macro` crates can only export
+//! procedural macros.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+#[proc_macro_derive(Template, attributes(template))]
+pub fn derive_template(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let path = template_path(input)?;
+    let fields = field_names(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let check = format_ident!(
+        "{}_has_every_template_variable",
+        snake_case(&name.to_string())
+    );
+
+    Ok(quote! {
+        impl #impl_generics ::macros::Template for #name #ty_generics #where_clause {
+            const PATH: &'static str = #path;
+        }
+
+        #[cfg(test)]
+        #[test]
+        fn #check() {
+            ::macros::assert_template_fields(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/src/templates"),
+                #path,
+                &[#(#fields),*],
+            );
+        }
+    })
+}
+
+// `#[template(path = "users/get.html")]`
+fn template_path(input: &DeriveInput) -> syn::Result<LitStr> {
+    let mut path = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("template")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("path") {
+                path = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `path = \"...\"`"))
+            }
+        })?;
+    }
+    path.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "missing `#[template(path = \"...\")]`, relative to `src/templates/`",
+        )
+    })
+}
+
+// the names the fields serialize to, which is what the template sees
+fn field_names(input: &DeriveInput) -> syn::Result<Vec<String>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs can be template contexts",
+        ));
+    };
+    let fields = match &data.fields {
+        Fields::Named(fields) => &fields.named,
+        // serde makes `null` of unit structs and Tera wants an object
+        Fields::Unit | Fields::Unnamed(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "template contexts need named fields, write `struct Name {}` for none",
+            ));
+        }
+    };
+
+    let mut names = vec![];
+    for field in fields {
+        let mut name = field
+            .ident
+            .as_ref()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("flatten") {
+                    return Err(meta.error(
+                        "flattened fields cannot be checked against the template, name them",
+                    ));
+                } else if meta.input.peek(syn::Token![=]) {
+                    meta.value()?.parse::<syn::Expr>()?;
+                }
+                Ok(())
+            })?;
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -1,3 +1,8 @@
+mod template;
+
+pub use macros_derive::Template;
+pub use template::{assert_template_fields, Template};
+
 #[macro_export]
 macro_rules! ert {
     () => {
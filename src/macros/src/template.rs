@@ -0,0 +1,207 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+
+// not covered by semver, only used by the tests `#[derive(Template)]` adds
+use tera::ast::{Expr, ExprVal, Node};
+
+/// The context of the Tera template at `PATH`, relative to `src/templates/`.
+///
+/// `#[derive(Template)]` with `#[template(path = "users/get.html")]`
+/// implements it and adds a test failing when the template, or anything it
+/// includes or extends, uses a variable the struct does not have. Typos in
+/// templates would otherwise render as nothing.
+pub trait Template {
+    const PATH: &'static str;
+}
+
+/// Panics naming every variable the template at `path` below `dir` uses that
+/// is not one of `fields`. Included templates and layouts see the same
+/// context and are checked along. Macros only see their arguments, so the
+/// ones defined or imported along the way are checked against those.
+pub fn assert_template_fields(dir: &str, path: &str, fields: &[&str]) {
+    let mut walker = Walker::new(Path::new(dir));
+    walker.template(path);
+
+    let missing: Vec<_> = walker
+        .used
+        .iter()
+        .filter(|v| !fields.contains(&v.as_str()))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "`{path}` uses {missing:?}, which its context does not have (it has {fields:?})"
+    );
+    assert!(
+        walker.undeclared.is_empty(),
+        "`{path}` uses macros with variables they have no argument for: {:?}",
+        walker.undeclared
+    );
+}
+
+struct Walker<'a> {
+    dir: &'a Path,
+    seen: HashSet<String>,
+    used: BTreeSet<String>,
+    /// `macro in file uses variable` for macro bodies
+    undeclared: BTreeSet<String>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(dir: &'a Path) -> Self {
+        Self {
+            dir,
+            seen: HashSet::new(),
+            used: BTreeSet::new(),
+            undeclared: BTreeSet::new(),
+        }
+    }
+
+    fn parse(&self, name: &str) -> tera::Template {
+        let file = self.dir.join(name);
+        let source = std::fs::read_to_string(&file)
+            .unwrap_or_else(|e| panic!("cannot read template {}: {e}", file.display()));
+        tera::Template::new(name, None, &source)
+            .unwrap_or_else(|e| panic!("cannot parse template `{name}`: {e:?}"))
+    }
+
+    // only the macros of an imported file matter, not what is around them
+    fn macro_file(&mut self, name: &str) {
+        if !self.seen.insert(format!("macros {name}")) {
+            return;
+        }
+        let template = self.parse(name);
+        for node in &template.ast {
+            match node {
+                Node::MacroDefinition(..) | Node::ImportMacro(..) => {
+                    self.node(name, node, &mut vec![])
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn template(&mut self, name: &str) {
+        if !self.seen.insert(name.to_owned()) {
+            return;
+        }
+        let template = self.parse(name);
+
+        self.nodes(name, &template.ast, &mut vec![]);
+        if let Some(parent) = &template.parent {
+            self.template(parent);
+        }
+    }
+
+    fn nodes(&mut self, file: &str, nodes: &[Node], locals: &mut Vec<String>) {
+        for node in nodes {
+            self.node(file, node, locals);
+        }
+    }
+
+    fn node(&mut self, file: &str, node: &Node, locals: &mut Vec<String>) {
+        match node {
+            Node::VariableBlock(_, expr) => self.expr(expr, locals),
+            Node::Include(_, names, _) => {
+                for name in names {
+                    self.template(name);
+                }
+            }
+            Node::Set(_, set) => {
+                self.expr(&set.value, locals);
+                locals.push(set.key.clone());
+            }
+            Node::FilterSection(_, section, _) => {
+                section
+                    .filter
+                    .args
+                    .values()
+                    .for_each(|e| self.expr(e, locals));
+                self.nodes(file, &section.body, locals);
+            }
+            Node::Block(_, block, _) => self.nodes(file, &block.body, locals),
+            Node::Forloop(_, forloop, _) => {
+                self.expr(&forloop.container, locals);
+                let scope = locals.len();
+                locals.extend(forloop.key.clone());
+                locals.push(forloop.value.clone());
+                locals.push("loop".to_owned());
+                self.nodes(file, &forloop.body, locals);
+                locals.truncate(scope);
+                if let Some(empty) = &forloop.empty_body {
+                    self.nodes(file, empty, locals);
+                }
+            }
+            Node::If(cond, _) => {
+                for (_, expr, body) in &cond.conditions {
+                    self.expr(expr, locals);
+                    self.nodes(file, body, locals);
+                }
+                if let Some((_, body)) = &cond.otherwise {
+                    self.nodes(file, body, locals);
+                }
+            }
+            Node::MacroDefinition(_, definition, _) => {
+                let mut body = Walker::new(self.dir);
+                let mut args = definition.args.keys().cloned().collect();
+                body.nodes(file, &definition.body, &mut args);
+                self.undeclared.extend(
+                    body.used
+                        .iter()
+                        .map(|v| format!("`{}` in `{file}` uses `{v}`", definition.name)),
+                );
+                self.undeclared.append(&mut body.undeclared);
+            }
+            Node::ImportMacro(_, name, _) => self.macro_file(name),
+            Node::Extends(..)
+            | Node::Super
+            | Node::Text(_)
+            | Node::Raw(..)
+            | Node::Break(_)
+            | Node::Continue(_)
+            | Node::Comment(..) => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, locals: &[String]) {
+        self.val(&expr.val, locals);
+        for filter in &expr.filters {
+            filter.args.values().for_each(|e| self.expr(e, locals));
+        }
+    }
+
+    fn val(&mut self, val: &ExprVal, locals: &[String]) {
+        match val {
+            ExprVal::Ident(ident) => self.ident(ident, locals),
+            ExprVal::Math(e) => {
+                self.expr(&e.lhs, locals);
+                self.expr(&e.rhs, locals);
+            }
+            ExprVal::Logic(e) => {
+                self.expr(&e.lhs, locals);
+                self.expr(&e.rhs, locals);
+            }
+            ExprVal::In(e) => {
+                self.expr(&e.lhs, locals);
+                self.expr(&e.rhs, locals);
+            }
+            ExprVal::Test(test) => {
+                self.ident(&test.ident, locals);
+                test.args.iter().for_each(|e| self.expr(e, locals));
+            }
+            ExprVal::MacroCall(call) => call.args.values().for_each(|e| self.expr(e, locals)),
+            ExprVal::FunctionCall(call) => call.args.values().for_each(|e| self.expr(e, locals)),
+            ExprVal::Array(items) => items.iter().for_each(|e| self.expr(e, locals)),
+            ExprVal::StringConcat(concat) => concat.values.iter().for_each(|v| self.val(v, locals)),
+            ExprVal::String(_) | ExprVal::Int(_) | ExprVal::Float(_) | ExprVal::Bool(_) => {}
+        }
+    }
+
+    // `post.user_id` and `counts[r.reaction]` use `post` and `counts`
+    fn ident(&mut self, ident: &str, locals: &[String]) {
+        let root = ident.split(['.', '[']).next().unwrap_or_default();
+        if root.is_empty() || root == "__tera_context" || locals.iter().any(|l| l == root) {
+            return;
+        }
+        self.used.insert(root.to_owned());
+    }
+}
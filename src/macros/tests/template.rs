@@ -0,0 +1,20 @@
+use macros::assert_template_fields;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+#[test]
+fn macros_may_use_their_arguments_and_locals() {
+    assert_template_fields(FIXTURES, "declared.html", &["user", "title"]);
+}
+
+#[test]
+#[should_panic(expected = "`shout` in `undeclared.html` uses `title`")]
+fn macros_may_not_use_the_context() {
+    assert_template_fields(FIXTURES, "undeclared.html", &["title"]);
+}
+
+#[test]
+#[should_panic(expected = "`farewell` in `broken_macros.html` uses `user`")]
+fn imported_macros_are_checked_too() {
+    assert_template_fields(FIXTURES, "imported.html", &["user"]);
+}
@@ -1,18 +1,23 @@
 mod background;
 mod config;
 mod error;
+mod i18n;
 mod middleware;
 mod models;
 mod routes;
 mod schema;
 mod services;
+mod templating;
+mod views;
 
+use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
 use axum::Router;
 use axum::http::header;
+use axum_extra::extract::cookie::Key;
 
 use config::Env;
 use diesel::Connection;
@@ -20,30 +25,41 @@ use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::pooled_connection::deadpool as diesel_deadpool;
 use diesel_async::pooled_connection::deadpool::Hook;
 
-use figment::{Figment, providers::Format};
-
 use error::AppError;
-use notify::Watcher;
+use services::blobs::AppBlobStore;
+use services::content_filter::ContentFilterChain;
+use services::follows::FollowServiceDb;
+use services::moderation::ModerationServiceDb;
+use services::notifications::NotificationServiceDb;
+use services::posts::PostServiceDb;
 use services::users::UserServiceDb;
-use tera::Tera;
 use tokio::spawn;
 use tokio::sync::RwLock;
 use tower::ServiceBuilder;
 use tower_http::compression::CompressionLayer;
+use tower_http::services::{ServeDir, ServeFile};
 use tower_http::set_header::SetResponseHeaderLayer;
 use tracing::*;
 use tracing_forest::ForestLayer;
 use tracing_subscriber::{EnvFilter, prelude::*};
 
+use crate::background::live_reload::LiveReload;
+use crate::background::post_scheduler::PostScheduler;
 use crate::background::posts_broker::PostsBroker;
+use crate::middleware::csrf::CsrfExt;
+use crate::middleware::errors::ErrorPagesExt;
+use crate::middleware::live_reload::LiveReloadExt;
+use crate::middleware::locale::LocaleExt;
 use crate::middleware::logging::HttpLoggingExt;
+use crate::middleware::rate_limit::{RateLimitExt, RateLimiter};
+use crate::middleware::session::SessionExt;
+
+// the scripts and styles built by Parcel
+const ASSETS_DIR: &str = "./dist/";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cfg: config::AppCfg = Figment::new()
-        .merge(figment::providers::Json::file("appsettings.json"))
-        .merge(figment::providers::Env::prefixed("APP_"))
-        .extract()?;
+    let cfg = config::AppCfg::load()?;
 
     tracing_subscriber::registry()
         .with(EnvFilter::from_default_env())
@@ -54,11 +70,11 @@ async fn main() -> anyhow::Result<()> {
 
     // create a new connection pool with the default config
     let mgr =
-        AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new(&cfg.database_url);
+        AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new(&cfg.database.url);
 
     info!("Starting DB pool");
     let pgpool = diesel_deadpool::Pool::builder(mgr)
-        .max_size(10)
+        .max_size(cfg.database.pool_size)
         .pre_recycle(Hook::async_fn(|conn, metrics| {
             tracing::trace_span!("dbpool::pre_recycle").in_scope(|| {
                 let c = std::ptr::addr_of!(conn);
@@ -77,7 +93,7 @@ async fn main() -> anyhow::Result<()> {
 
     const MIGRATIONS: diesel_migrations::EmbeddedMigrations =
         diesel_migrations::embed_migrations!("migrations/");
-    let mut conn = diesel::PgConnection::establish(&cfg.database_url)?;
+    let mut conn = diesel::PgConnection::establish(&cfg.database.url)?;
     let mig_res =
         <diesel::PgConnection as diesel_migrations::MigrationHarness<_>>::run_pending_migrations(
             &mut conn, MIGRATIONS,
@@ -88,68 +104,58 @@ async fn main() -> anyhow::Result<()> {
     }
 
     let user_svc = UserServiceDb::new(pgpool.clone());
+    let post_svc = PostServiceDb::new(pgpool.clone());
+    let follow_svc = FollowServiceDb::new(pgpool.clone());
+    let notification_svc = NotificationServiceDb::new(pgpool.clone());
+    let moderation_svc = ModerationServiceDb::new(pgpool.clone());
+    let blob_store = AppBlobStore::from_cfg(&cfg.blobs)?;
+    let rate_limiter = RateLimiter::from_cfg(&cfg.rate_limit, pgpool.clone())?;
+    let content_filters = ContentFilterChain::from_cfg(&cfg.content_filter, post_svc.clone());
 
-    let tera: Arc<RwLock<_>> = Arc::new(Tera::new("src/templates/**/*")?.into());
+    let session_key = match &cfg.server.session_key {
+        Some(key) => Key::from(key.as_bytes()),
+        None => {
+            warn!("no server.session_key, everybody is signed out when the app stops");
+            Key::generate()
+        }
+    };
 
-    let mut tera_watcher = None;
-    if cfg.env == Env::Development {
-        info!("Development mode: setting up tera watcher");
+    i18n::init()?;
+    let tera: Arc<RwLock<_>> = Arc::new(templating::load(&cfg.env)?.into());
 
-        struct TeraTemplateWatcher {
-            pub tera: Arc<RwLock<Tera>>,
-            tokio_spawn_handle: tokio::runtime::Handle,
-        }
-        impl notify::EventHandler for TeraTemplateWatcher {
-            fn handle_event(&mut self, event: notify::Result<notify::Event>) {
-                match event {
-                    Ok(e)
-                        if e.kind
-                            == notify::EventKind::Modify(notify::event::ModifyKind::Metadata(
-                                notify::event::MetadataKind::Any,
-                            ))
-                            || e.kind
-                                == notify::EventKind::Remove(notify::event::RemoveKind::File) =>
-                    {
-                        info!(?e, "tera file touched");
-                        let s = self.tera.clone();
-                        self.tokio_spawn_handle.spawn(async move {
-                            let _ = s.write().await.full_reload();
-                        });
-                    }
-                    Err(e) => error!(%e, "issue with watching tera templates"),
-                    _ => {}
-                }
-            }
-        }
-        let tera_clone_watch = tera.clone();
-        tera_watcher.replace(notify::recommended_watcher(TeraTemplateWatcher {
-            tera: tera_clone_watch,
-            tokio_spawn_handle: tokio::runtime::Handle::current(),
-        })?);
-    }
-    if let Some(w) = tera_watcher.as_mut() {
-        let _ = w
-            .watch(
-                Path::new("src/templates/"),
-                notify::RecursiveMode::Recursive,
-            )
-            .inspect_err(|e| error!(%e, "issue with thing"));
-    }
+    // kept alive for as long as the app runs
+    let (live_reload, _watcher) = if cfg.env == Env::Development {
+        info!("Development mode: watching templates and assets");
+        let (live_reload, watcher) = LiveReload::watch(
+            tera.clone(),
+            Path::new(templating::TEMPLATES_DIR),
+            Path::new(ASSETS_DIR),
+        )?;
+        (Some(live_reload), Some(watcher))
+    } else {
+        (None, None)
+    };
 
     let lapin_mgr = deadpool_lapin::Manager::new(
-        &cfg.rabbitmq_url,
+        &cfg.rabbitmq.url,
         lapin::ConnectionProperties::default()
             .with_executor(tokio_executor_trait::Tokio::current())
             .with_reactor(tokio_reactor_trait::Tokio),
     );
     let lapin_pool = deadpool_lapin::Pool::builder(lapin_mgr)
         .runtime(deadpool::Runtime::Tokio1)
-        .create_timeout(Some(Duration::from_secs(5)))
-        .max_size(100)
+        .create_timeout(Some(Duration::from_secs(cfg.rabbitmq.create_timeout_secs)))
+        .max_size(cfg.rabbitmq.pool_size)
         .build()?;
 
-    let posts_subscriber_mgr = Arc::new(background::posts_broker::PostsSubscriptionManager::new());
-    let posts_broker = PostsBroker::new(posts_subscriber_mgr.clone(), lapin_pool.clone());
+    let posts_subscriber_mgr = Arc::new(background::posts_broker::PostsSubscriptionManager::new(
+        &cfg.posts_broker,
+    ));
+    let posts_broker = PostsBroker::new(
+        posts_subscriber_mgr.clone(),
+        lapin_pool.clone(),
+        &cfg.posts_broker,
+    );
 
     // start posts broker background
     let _posts_broker_jhandle = spawn(
@@ -158,36 +164,141 @@ async fn main() -> anyhow::Result<()> {
             .run(), // .instrument(info_span!("posts_broker_run")),
     );
 
+    // publish scheduled posts when they come due
+    let _post_scheduler_jhandle = spawn(
+        PostScheduler::new(
+            post_svc.clone(),
+            notification_svc.clone(),
+            lapin_pool.clone(),
+        )
+        .instrument(info_span!("post_scheduler_run"))
+        .run(),
+    );
+
+    // a reloaded page has to pick up rebuilt assets in development
+    let assets_cache_control = if cfg.env == Env::Development {
+        header::HeaderValue::from_static("no-cache")
+    } else {
+        header::HeaderValue::from_str(&format!("max-age={}", cfg.server.assets_max_age_secs))?
+    };
+    // the entries keep their names across builds, so browsers revalidate them
+    // each time; what they pull in is named after its content
+    let entry_asset = |file: &str| {
+        ServiceBuilder::new()
+            .layer(SetResponseHeaderLayer::overriding(
+                header::CACHE_CONTROL,
+                header::HeaderValue::from_static("no-cache"),
+            ))
+            .layer(CompressionLayer::new())
+            .service(ServeFile::new(Path::new(ASSETS_DIR).join(file)))
+    };
     let app = Router::new()
-        .route_service(
-            "/",
+        .merge(routes::home::router().with_state(tera.clone()))
+        .merge(routes::locale::router())
+        .route_service("/index.js", entry_asset("index.js"))
+        .route_service("/main.css", entry_asset("main.css"))
+        .fallback_service(
             ServiceBuilder::new()
                 .layer(SetResponseHeaderLayer::if_not_present(
                     header::CACHE_CONTROL,
-                    header::HeaderValue::from_static("max-age=13420"),
+                    assets_cache_control,
                 ))
                 .layer(CompressionLayer::new())
-                .service(tower_http::services::ServeDir::new("./dist/")),
+                .service(ServeDir::new(ASSETS_DIR)),
         )
-        .route(
+        .nest(
             "/users",
-            routes::users::router().with_state((user_svc.clone(), tera.clone())),
+            routes::users::router()
+                .with_state((
+                    user_svc.clone(),
+                    tera.clone(),
+                    follow_svc.clone(),
+                    posts_subscriber_mgr.clone(),
+                    notification_svc.clone(),
+                    lapin_pool.clone(),
+                ))
+                .merge(routes::avatars::router().with_state((
+                    user_svc.clone(),
+                    blob_store.clone(),
+                    tera.clone(),
+                ))),
         )
         .nest(
             "/posts",
-            routes::posts::router().with_state((
+            routes::posts::router()
+                .with_state((
+                    tera.clone(),
+                    posts_subscriber_mgr.clone(),
+                    lapin_pool.clone(),
+                    pgpool.clone(),
+                    post_svc.clone(),
+                    follow_svc.clone(),
+                    notification_svc.clone(),
+                    blob_store.clone(),
+                    moderation_svc.clone(),
+                    content_filters.clone(),
+                ))
+                .merge(routes::revisions::router().with_state((
+                    tera.clone(),
+                    post_svc.clone(),
+                    moderation_svc.clone(),
+                    content_filters.clone(),
+                    lapin_pool.clone(),
+                ))),
+        )
+        .nest(
+            "/moderation",
+            routes::moderation::router().with_state((
                 tera.clone(),
-                posts_subscriber_mgr.clone(),
+                moderation_svc.clone(),
+                blob_store.clone(),
+                lapin_pool.clone(),
+                notification_svc.clone(),
+            )),
+        )
+        .nest(
+            "/api/v1",
+            routes::api::v1::router().with_state((
+                user_svc.clone(),
+                post_svc.clone(),
+                moderation_svc.clone(),
+                notification_svc.clone(),
+                content_filters,
                 lapin_pool.clone(),
-                pgpool.clone(),
             )),
         )
-        .with_http_logging();
+        .merge(routes::api::docs_router(&cfg.env))
+        .nest("/blobs", routes::blobs::router().with_state(blob_store))
+        .nest(
+            "/notifications",
+            routes::notifications::router().with_state((
+                tera.clone(),
+                notification_svc.clone(),
+                lapin_pool.clone(),
+            )),
+        )
+        // every route, whichever router it came from
+        .with_csrf_check()
+        .with_session(session_key)
+        .with_rate_limit(rate_limiter)
+        .with_error_pages(tera.clone())
+        // error pages have the token too
+        .with_csrf_token()
+        .with_locale();
+    let app = match live_reload {
+        Some(live_reload) => app.with_live_reload(live_reload),
+        None => app,
+    }
+    .with_http_logging();
 
-    let addr = "0.0.0.0:3000";
+    let addr = cfg.server.bind;
     let listener = tokio::net::TcpListener::bind(addr).await?;
     info!("starting listening at {}", addr);
-    axum::serve(listener, app.into_make_service()).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
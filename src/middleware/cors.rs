@@ -1,4 +1,4 @@
-use axum::{http::Method, Router};
+use axum::{Router, http::Method};
 use tower_http::cors::{self, CorsLayer};
 
 #[allow(dead_code)]
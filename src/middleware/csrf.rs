@@ -0,0 +1,178 @@
+//! CSRF protection with a double submit cookie. Every browser gets a random
+//! token in the `csrf_token` cookie, which the page sends back in the
+//! `X-CSRF-Token` header of each htmx request (see `hx-headers` in
+//! `layouts/base.html`). Another site can make the browser send the cookie,
+//! but not read it or the page to set the header.
+use std::fmt::Write;
+
+use axum::Router;
+use axum::extract::Request;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use rand::RngCore;
+
+use crate::AppError;
+
+pub const COOKIE: &str = "csrf_token";
+pub const HEADER: &str = "x-csrf-token";
+
+tokio::task_local! {
+    static TOKEN: String;
+}
+
+/// The token of the request being handled, for the `csrf_token()` Tera
+/// function. `None` outside of requests.
+pub fn current_token() -> Option<String> {
+    TOKEN.try_with(Clone::clone).ok()
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    bytes.iter().fold(String::with_capacity(64), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+/// Hands out a token to browsers without one and makes the current one
+/// available to templates.
+pub async fn issue_token(jar: CookieJar, req: Request, next: Next) -> Response {
+    if let Some(token) = jar.get(COOKIE).map(|c| c.value().to_owned()) {
+        return TOKEN.scope(token, next.run(req)).await;
+    }
+
+    let token = new_token();
+    let res = TOKEN.scope(token.clone(), next.run(req)).await;
+    let cookie = Cookie::build((COOKIE, token))
+        .path("/")
+        .same_site(SameSite::Strict)
+        .http_only(true)
+        .permanent();
+    (jar.add(cookie), res).into_response()
+}
+
+/// Rejects state changing requests whose `X-CSRF-Token` header does not
+/// match their cookie.
+pub async fn check_token(jar: CookieJar, req: Request, next: Next) -> Response {
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if safe {
+        return next.run(req).await;
+    }
+
+    let sent = req.headers().get(HEADER).map(|v| v.as_bytes());
+    let expected = jar.get(COOKIE).map(|c| c.value().as_bytes());
+    match (sent, expected) {
+        (Some(sent), Some(expected)) if constant_time_eq(sent, expected) => next.run(req).await,
+        _ => AppError::forbidden("error-csrf").into_response(),
+    }
+}
+
+// how long a comparison takes gives nothing away about the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub trait CsrfExt<S> {
+    fn with_csrf_token(self) -> Self;
+    fn with_csrf_check(self) -> Self;
+}
+
+impl<S> CsrfExt<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// Add CSRF token issuing to Router
+    fn with_csrf_token(self) -> Router<S> {
+        self.layer(axum::middleware::from_fn(issue_token))
+    }
+
+    /// Add CSRF token checks to everything Router serves, fallback included
+    fn with_csrf_check(self) -> Router<S> {
+        self.layer(axum::middleware::from_fn(check_token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{StatusCode, header};
+    use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+    use diesel_async::pooled_connection::deadpool::Pool;
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::routes;
+    use crate::services::blobs::LocalBlobStore;
+    use crate::services::moderation::ModerationServiceDb;
+    use crate::services::notifications::NotificationServiceDb;
+
+    // the moderation routes wired up like in `main`; the pools never connect
+    // since the check turns requests away before any handler runs
+    fn app() -> Router {
+        let db = Pool::builder(AsyncDieselConnectionManager::new("postgres://unused"))
+            .build()
+            .unwrap();
+        let rmq = deadpool_lapin::Pool::builder(deadpool_lapin::Manager::new(
+            "amqp://unused",
+            lapin::ConnectionProperties::default(),
+        ))
+        .build()
+        .unwrap();
+        Router::new()
+            .nest(
+                "/moderation",
+                routes::moderation::router().with_state((
+                    Arc::new(RwLock::new(tera::Tera::default())),
+                    ModerationServiceDb::new(db.clone()),
+                    LocalBlobStore::new("unused"),
+                    rmq,
+                    NotificationServiceDb::new(db),
+                )),
+            )
+            .with_csrf_check()
+            .with_csrf_token()
+    }
+
+    fn hide(cookie: Option<&str>, token: Option<&str>) -> Request {
+        let mut req = Request::post("/moderation/posts/00000000-0000-0000-0000-000000000000/hide")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        if let Some(cookie) = cookie {
+            req = req.header(header::COOKIE, format!("{COOKIE}={cookie}"));
+        }
+        if let Some(token) = token {
+            req = req.header(HEADER, token);
+        }
+        req.body(Body::from("user_id=1")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn moderation_needs_the_token() {
+        for req in [
+            hide(None, None),
+            hide(Some("abc"), None),
+            hide(None, Some("abc")),
+            hide(Some("abc"), Some("abd")),
+        ] {
+            let res = app().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_bearer_token_is_no_way_around_it() {
+        let mut req = hide(None, None);
+        req.headers_mut().insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_static("Bearer anything"),
+        );
+        let res = app().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -14,6 +14,8 @@
 use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse};
 use axum_macros::FromRequest;
 
+use crate::error::PublicError;
+
 // create an extractor that internally uses `axum::Json` but has a custom rejection
 #[derive(FromRequest)]
 #[from_request(via(axum::Json), rejection(ApiError))]
@@ -36,14 +38,14 @@ impl From<JsonRejection> for ApiError {
     }
 }
 
-// We implement `IntoResponse` so `ApiError` can be used as a response
+// We implement `IntoResponse` so `ApiError` can be used as a response, rendered
+// like any other error by `middleware::errors`
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
-        let payload = serde_json::json!({
-            "message": self.message,
-            "origin": "derive_from_request"
-        });
-
-        (self.status, axum::Json(payload)).into_response()
+        PublicError {
+            status: self.status,
+            message: self.message,
+        }
+        .into_response()
     }
 }
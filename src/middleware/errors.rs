@@ -0,0 +1,204 @@
+//! Renders error responses in the shape the client asked for: the
+//! `error.html` page for browsers, a fragment swapped into `#errors` for htmx
+//! requests and RFC 7807 `application/problem+json` for API clients.
+//!
+//! Every request gets a correlation id, taken from `X-Request-Id` when the
+//! client or a proxy sent a sane one. It is logged with everything handling
+//! the request, sent back in `X-Request-Id` and shown with every error, so a
+//! user reporting one can be matched to the logs.
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use macros::ert;
+use serde::Serialize;
+use tera::Tera;
+use tokio::sync::RwLock;
+use tracing::{Instrument, error, info_span};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::PublicError;
+use crate::i18n::Message;
+use crate::templating;
+use crate::views::{ErrorFragment, ErrorPage};
+
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+// where htmx errors go, see `src/templates/layouts/base.html`
+const HTMX_ERROR_TARGET: &str = "#errors";
+// bodies of error responses not made from a `PublicError`, e.g. extractor
+// rejections, are shown when at most this long
+const MAX_BARE_BODY_BYTES: usize = 4 * 1024;
+
+/// An RFC 7807 `application/problem+json` error body.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    #[schema(example = "about:blank")]
+    pub type_: &'static str,
+    /// the reason phrase of `status`, in the locale of the request
+    #[schema(example = "Not Found")]
+    pub title: String,
+    #[schema(example = 404)]
+    pub status: u16,
+    /// what went wrong, for humans
+    #[schema(example = "post not found")]
+    pub detail: String,
+    /// the path of the request
+    pub instance: String,
+    /// also sent in `X-Request-Id`, to find the request in the logs
+    pub correlation_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    Page,
+    Htmx,
+    Problem,
+}
+
+impl ErrorFormat {
+    fn of(req: &Request) -> Self {
+        let headers = req.headers();
+        if headers.get("hx-request").is_some_and(|v| v == "true") {
+            return Self::Htmx;
+        }
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        let wants_json =
+            accept.contains("application/json") || accept.contains("application/problem+json");
+        if req.uri().path().starts_with("/api/") || (wants_json && !accept.contains("text/html")) {
+            Self::Problem
+        } else {
+            Self::Page
+        }
+    }
+}
+
+fn correlation_id(headers: &HeaderMap) -> String {
+    headers
+        .get(&REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| {
+            (1..=64).contains(&id.len())
+                && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+        .map_or_else(|| Uuid::now_v7().to_string(), str::to_owned)
+}
+
+pub async fn render_errors(
+    State(tera): State<Arc<RwLock<Tera>>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let id = correlation_id(req.headers());
+    let format = ErrorFormat::of(&req);
+    let path = req.uri().path().to_owned();
+
+    let mut res = next
+        .run(req)
+        .instrument(info_span!("request", correlation_id = %id))
+        .await;
+    if let Ok(v) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID, v);
+    }
+    let status = res.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return res;
+    }
+
+    // the reason phrase, in the locale of the request
+    let title = Message::new("error-title")
+        .arg("status", u64::from(status.as_u16()))
+        .localize();
+    let (mut parts, body) = res.into_parts();
+    let public = match parts.extensions.remove::<PublicError>() {
+        Some(public) => public,
+        None => {
+            // from axum itself, e.g. a rejected extractor or an unknown route
+            let text = axum::body::to_bytes(body, MAX_BARE_BODY_BYTES)
+                .await
+                .ok()
+                .and_then(|b| String::from_utf8(b.to_vec()).ok())
+                .filter(|t| !t.trim().is_empty());
+            PublicError {
+                status,
+                message: text.unwrap_or_else(|| title.clone()),
+            }
+        }
+    };
+
+    let (content_type, body) = match format {
+        ErrorFormat::Problem => {
+            let problem = Problem {
+                type_: "about:blank",
+                title,
+                status: status.as_u16(),
+                detail: public.message,
+                instance: path,
+                correlation_id: id,
+            };
+            let body = serde_json::to_string(&problem)
+                .inspect_err(ert!())
+                .unwrap_or_default();
+            ("application/problem+json", body)
+        }
+        ErrorFormat::Htmx | ErrorFormat::Page => {
+            let tera = tera.read().await;
+            let rendered = if format == ErrorFormat::Htmx {
+                parts
+                    .headers
+                    .insert("hx-retarget", HeaderValue::from_static(HTMX_ERROR_TARGET));
+                parts
+                    .headers
+                    .insert("hx-reswap", HeaderValue::from_static("innerHTML"));
+                let view = ErrorFragment {
+                    status: status.as_u16(),
+                    title: &title,
+                    message: &public.message,
+                    correlation_id: &id,
+                };
+                templating::render(&tera, &view)
+            } else {
+                let view = ErrorPage {
+                    status: status.as_u16(),
+                    title: &title,
+                    message: &public.message,
+                    correlation_id: &id,
+                };
+                templating::render(&tera, &view)
+            };
+            // the error page failing should not hide the error itself
+            let html = rendered.inspect_err(ert!()).unwrap_or_else(|_| {
+                format!("<p>{} (ref {id})</p>", tera::escape_html(&public.message))
+            });
+            ("text/html; charset=utf-8", html)
+        }
+    };
+
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+pub trait ErrorPagesExt<S> {
+    fn with_error_pages(self, tera: Arc<RwLock<Tera>>) -> Self;
+}
+
+impl<S> ErrorPagesExt<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// Add content negotiated error responses to Router
+    fn with_error_pages(self, tera: Arc<RwLock<Tera>>) -> Router<S> {
+        self.layer(axum::middleware::from_fn_with_state(tera, render_errors))
+    }
+}
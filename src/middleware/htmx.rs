@@ -0,0 +1,215 @@
+//! htmx aware responses. Handlers render fragments meant to be swapped into
+//! the start page; `HxRequest::render` sends those as they are to htmx and
+//! wraps them in `layouts/page.html` when the URL is opened directly, e.g.
+//! from the address bar or after `HX-Push-Url`, or when htmx restores a page
+//! missing from its history cache.
+//!
+//! `HxTrigger`, `HxPushUrl`, `HxRedirect` and `HxRefresh` set the response
+//! headers of the same name; return them next to the body, e.g.
+//! `(HxTrigger::event("x"), html)`.
+use std::convert::Infallible;
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+use axum::response::{Html, IntoResponse, IntoResponseParts, Response, ResponseParts};
+use macros::Template;
+use serde::Serialize;
+use tera::Tera;
+
+use crate::i18n::Message;
+use crate::{AppError, templating, views};
+
+const HX_REQUEST: HeaderName = HeaderName::from_static("hx-request");
+const HX_TARGET: HeaderName = HeaderName::from_static("hx-target");
+const HX_BOOSTED: HeaderName = HeaderName::from_static("hx-boosted");
+const HX_HISTORY_RESTORE_REQUEST: HeaderName =
+    HeaderName::from_static("hx-history-restore-request");
+const HX_TRIGGER: HeaderName = HeaderName::from_static("hx-trigger");
+const HX_PUSH_URL: HeaderName = HeaderName::from_static("hx-push-url");
+const HX_REDIRECT: HeaderName = HeaderName::from_static("hx-redirect");
+const HX_REFRESH: HeaderName = HeaderName::from_static("hx-refresh");
+
+/// What htmx told us about the request. Never rejects, requests without the
+/// headers are plain navigations.
+#[derive(Debug, Clone, Default)]
+pub struct HxRequest {
+    /// `HX-Request`, sent with every request htmx makes
+    pub is_htmx: bool,
+    /// `HX-Target`, the id of the element the response is swapped into
+    pub target: Option<String>,
+    /// `HX-Boosted`, a link or form boosted with `hx-boost`, which swaps the
+    /// whole `<body>`
+    pub boosted: bool,
+    /// `HX-History-Restore-Request`, going back to a page htmx no longer has
+    /// in its history cache, which it swaps into the whole `<body>`
+    pub history_restore: bool,
+}
+
+impl HxRequest {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let flag = |name| headers.get(name).is_some_and(|v| v == "true");
+        Self {
+            is_htmx: flag(&HX_REQUEST),
+            target: headers
+                .get(&HX_TARGET)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned),
+            boosted: flag(&HX_BOOSTED),
+            history_restore: flag(&HX_HISTORY_RESTORE_REQUEST),
+        }
+    }
+
+    /// Whether to answer with the fragment alone. Boosted requests and
+    /// history restores get the full page like any navigation.
+    pub fn wants_fragment(&self) -> bool {
+        self.is_htmx && !self.boosted && !self.history_restore
+    }
+
+    /// Whether the response is swapped into the element with this id.
+    pub fn targets(&self, id: &str) -> bool {
+        self.target.as_deref() == Some(id)
+    }
+
+    /// Renders `view`, wrapped in `layouts/page.html` titled with the message
+    /// `title` unless htmx asked for the fragment.
+    pub fn render<V: Template + Serialize>(
+        &self,
+        tera: &Tera,
+        title: &'static str,
+        view: &V,
+    ) -> Result<HxPage, AppError> {
+        let fragment = templating::render(tera, view)?;
+        if self.wants_fragment() {
+            return Ok(HxPage(Html(fragment)));
+        }
+        let page = views::Page {
+            title: &Message::new(title).localize(),
+            content: &fragment,
+        };
+        Ok(HxPage(Html(templating::render(tera, &page)?)))
+    }
+}
+
+/// What `HxRequest::render` made of a view, the fragment or the whole page.
+/// Sent with `Vary: HX-Request`, so the browser and caches in between never
+/// answer a navigation with a fragment they stored for htmx.
+#[derive(Debug, Clone)]
+pub struct HxPage(pub Html<String>);
+
+impl IntoResponse for HxPage {
+    fn into_response(self) -> Response {
+        let mut res = self.0.into_response();
+        res.headers_mut()
+            .append(header::VARY, HeaderValue::from_static("HX-Request"));
+        res
+    }
+}
+
+impl<S> FromRequestParts<S> for HxRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+fn set_header(
+    mut res: ResponseParts,
+    name: HeaderName,
+    value: &str,
+) -> Result<ResponseParts, AppError> {
+    let value =
+        HeaderValue::from_str(value).map_err(|e| anyhow::anyhow!("invalid {name} header: {e}"))?;
+    res.headers_mut().insert(name, value);
+    Ok(res)
+}
+
+/// `HX-Trigger`: events htmx fires on the target once the response arrives,
+/// e.g. for `hx-trigger="userCreated from:body"` elsewhere on the page.
+#[derive(Debug, Clone)]
+pub struct HxTrigger(String);
+
+impl HxTrigger {
+    pub fn event(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+}
+
+impl IntoResponseParts for HxTrigger {
+    type Error = AppError;
+
+    fn into_response_parts(self, res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        set_header(res, HX_TRIGGER, &self.0)
+    }
+}
+
+/// `HX-Push-Url`: puts this URL in the address bar and history, so reloading
+/// or going back opens what was swapped in as a page of its own.
+#[derive(Debug, Clone)]
+pub struct HxPushUrl(pub String);
+
+impl IntoResponseParts for HxPushUrl {
+    type Error = AppError;
+
+    fn into_response_parts(self, res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        set_header(res, HX_PUSH_URL, &self.0)
+    }
+}
+
+/// `HX-Redirect`: a full page load of this URL instead of a swap.
+// for handlers that leave the start page behind
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct HxRedirect(pub String);
+
+impl IntoResponseParts for HxRedirect {
+    type Error = AppError;
+
+    fn into_response_parts(self, res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        set_header(res, HX_REDIRECT, &self.0)
+    }
+}
+
+/// `HX-Refresh`: a full reload of the current page.
+#[derive(Debug, Clone)]
+pub struct HxRefresh;
+
+impl IntoResponseParts for HxRefresh {
+    type Error = AppError;
+
+    fn into_response_parts(self, res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        set_header(res, HX_REFRESH, "true")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[&'static str]) -> HxRequest {
+        let mut map = HeaderMap::new();
+        for name in headers {
+            map.insert(*name, HeaderValue::from_static("true"));
+        }
+        HxRequest::from_headers(&map)
+    }
+
+    #[test]
+    fn only_plain_htmx_requests_want_fragments() {
+        assert!(request(&["hx-request"]).wants_fragment());
+        assert!(!request(&[]).wants_fragment());
+        assert!(!request(&["hx-request", "hx-boosted"]).wants_fragment());
+        let restore = request(&["hx-request", "hx-history-restore-request"]);
+        assert!(!restore.wants_fragment());
+    }
+
+    #[test]
+    fn pages_vary_on_hx_request() {
+        let res = HxPage(Html("<p></p>".into())).into_response();
+        assert_eq!(res.headers()[header::VARY], "HX-Request");
+    }
+}
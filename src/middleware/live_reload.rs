@@ -0,0 +1,111 @@
+//! Development only: the browser side of `background::live_reload`. Full
+//! pages get a script that listens on the `/dev/live-reload` WebSocket and
+//! reloads the page, or only its stylesheets when just CSS changed. It also
+//! reloads once the server is back after a restart.
+use axum::Router;
+use axum::body::Body;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+use crate::background::live_reload::LiveReload;
+
+const SOCKET_PATH: &str = "/dev/live-reload";
+
+const SCRIPT: &str = r#"<script type="module">
+const connect = (restarted) => {
+  const ws = new WebSocket(`${location.protocol === "https:" ? "wss" : "ws"}://${location.host}/dev/live-reload`)
+  ws.onopen = () => restarted && location.reload()
+  ws.onmessage = (e) => {
+    if (JSON.parse(e.data) !== "css") {
+      location.reload()
+      return
+    }
+    for (const link of document.querySelectorAll('link[rel="stylesheet"]')) {
+      const url = new URL(link.href)
+      url.searchParams.set("reload", Date.now())
+      link.href = url
+    }
+  }
+  ws.onclose = () => setTimeout(() => connect(true), 1000)
+}
+connect(false)
+</script>
+"#;
+
+async fn socket(State(live_reload): State<LiveReload>, wsu: WebSocketUpgrade) -> Response {
+    wsu.on_upgrade(move |ws| forward(ws, live_reload))
+}
+
+async fn forward(mut ws: WebSocket, live_reload: LiveReload) {
+    let mut reloads = live_reload.subscribe();
+    loop {
+        tokio::select! {
+            reload = reloads.recv() => {
+                let reload = match reload {
+                    Ok(reload) => reload,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                };
+                let msg = serde_json::to_string(&reload).unwrap_or_default();
+                if ws.send(Message::Text(msg.into())).await.is_err() {
+                    return;
+                }
+            }
+            // the page went away
+            msg = ws.recv() => {
+                if !matches!(msg, Some(Ok(_))) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Adds `SCRIPT` to the end of every full page.
+pub async fn inject_script(req: Request, next: Next) -> Response {
+    let res = next.run(req).await;
+    let is_html = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if !is_html || res.headers().contains_key(header::CONTENT_ENCODING) {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let mut html = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => {
+            warn!(%e, "could not read the page to add the live reload script");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    // fragments for htmx have no `</body>` and are left alone
+    if let Some(end) = html.rfind("</body>") {
+        html.insert_str(end, SCRIPT);
+        parts.headers.remove(header::CONTENT_LENGTH);
+    }
+    Response::from_parts(parts, Body::from(html))
+}
+
+pub trait LiveReloadExt<S> {
+    fn with_live_reload(self, live_reload: LiveReload) -> Self;
+}
+
+impl<S> LiveReloadExt<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// Add the live reload script and its WebSocket to Router
+    fn with_live_reload(self, live_reload: LiveReload) -> Router<S> {
+        self.layer(axum::middleware::from_fn(inject_script))
+            .route_service(SOCKET_PATH, get(socket).with_state(live_reload))
+    }
+}
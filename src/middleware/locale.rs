@@ -0,0 +1,74 @@
+//! Picks the locale of each request: the one the user chose, kept in the
+//! `lang` cookie by `routes::locale`, or else the best match for their
+//! `Accept-Language`. Everything rendered while handling the request is in
+//! that locale, see `i18n`.
+use axum::Router;
+use axum::extract::Request;
+use axum::http::{HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::extract::CookieJar;
+use fluent_langneg::accepted_languages;
+use unic_langid::LanguageIdentifier;
+
+use crate::i18n;
+
+pub const COOKIE: &str = "lang";
+
+pub async fn negotiate(jar: CookieJar, req: Request, next: Next) -> Response {
+    let accept_language = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok());
+    let locale = pick(jar.get(COOKIE).map(|c| c.value()), accept_language);
+
+    let mut res = i18n::scope(locale.clone(), next.run(req)).await;
+    if let Ok(v) = HeaderValue::from_str(&locale.to_string()) {
+        res.headers_mut().insert(header::CONTENT_LANGUAGE, v);
+    }
+    res.headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept-language"));
+    res
+}
+
+/// The chosen locale if it is translated, otherwise the best one for
+/// `Accept-Language`.
+fn pick(chosen: Option<&str>, accept_language: Option<&str>) -> LanguageIdentifier {
+    let chosen = chosen.and_then(|c| c.parse::<LanguageIdentifier>().ok());
+    let accepted = accept_language
+        .map(accepted_languages::parse)
+        .unwrap_or_default();
+    i18n::negotiate(&chosen.into_iter().chain(accepted).collect::<Vec<_>>())
+}
+
+pub trait LocaleExt<S> {
+    fn with_locale(self) -> Self;
+}
+
+impl<S> LocaleExt<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// Add locale negotiation to Router
+    fn with_locale(self) -> Router<S> {
+        self.layer(axum::middleware::from_fn(negotiate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pick;
+    use crate::i18n;
+
+    #[test]
+    fn the_cookie_beats_accept_language() {
+        i18n::init().unwrap();
+        let pick = |chosen, accept_language| pick(chosen, accept_language).to_string();
+        assert_eq!(pick(Some("en"), Some("de")), "en");
+        assert_eq!(pick(Some("de"), Some("en-US,en;q=0.9")), "de");
+        // a cookie for a locale that is gone is ignored
+        assert_eq!(pick(Some("xx"), Some("fr, de;q=0.5")), "de");
+        assert_eq!(pick(None, Some("fr")), "en");
+        assert_eq!(pick(None, None), "en");
+    }
+}
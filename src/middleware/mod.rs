@@ -1,3 +1,10 @@
 pub mod cors;
+pub mod csrf;
 pub mod custom_json_extractor;
+pub mod errors;
+pub mod htmx;
+pub mod live_reload;
+pub mod locale;
 pub mod logging;
+pub mod rate_limit;
+pub mod session;
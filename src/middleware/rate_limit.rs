@@ -0,0 +1,615 @@
+//! Token bucket limits for the routes in `AppCfg::rate_limit`. Requests over
+//! the limit get a `429 Too Many Requests` with `Retry-After`.
+//!
+//! Buckets are kept as the time they are full again (the "theoretical arrival
+//! time" of GCRA), one timestamp per bucket instead of a token count and the
+//! time it was last refilled. Taking a token moves it `interval` later, and a
+//! request is let through as long as it stays within `burst` intervals from
+//! now.
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, bail};
+use axum::Router;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, FromRequest, Multipart, Request, State};
+use axum::http::{HeaderMap, Method, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use dashmap::DashMap;
+use diesel::sql_types::{Double, Text};
+use diesel::{OptionalExtension, QueryableByName};
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::AppError;
+use crate::config::{RateLimitBackend, RateLimitCfg, RateLimitKey, RouteLimit};
+use crate::i18n::Message;
+use crate::services::{Pool, Svc};
+
+// the memory store drops full buckets every this many requests
+const PRUNE_EVERY: usize = 1024;
+const PG_PRUNE_EVERY_SECS: u64 = 60;
+
+/// The shape of a bucket.
+#[derive(Debug, Clone, Copy)]
+pub struct Limit {
+    /// how long one token takes to come back
+    pub interval: Duration,
+    pub burst: u32,
+}
+
+impl Limit {
+    /// How far ahead of now a bucket may be full again, i.e. empty.
+    fn window(&self) -> Duration {
+        self.interval * self.burst
+    }
+}
+
+pub trait RateLimitStore<E = anyhow::Error>: Svc {
+    /// Takes a token from the bucket under `key`. `None` when there was one,
+    /// otherwise how long until there is.
+    fn take(
+        &self,
+        key: &str,
+        limit: Limit,
+    ) -> impl Future<Output = Result<Option<Duration>, E>> + Send;
+}
+
+/// Buckets in this process only. Each replica limits on its own.
+#[derive(Clone, Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Arc<DashMap<String, Instant>>,
+    takes: Arc<AtomicUsize>,
+}
+
+impl Svc for MemoryRateLimitStore {}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn take(&self, key: &str, limit: Limit) -> anyhow::Result<Option<Duration>> {
+        let now = Instant::now();
+        if self
+            .takes
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(PRUNE_EVERY)
+        {
+            // a bucket that is full again is the same as none at all
+            self.buckets.retain(|_, full_at| *full_at > now);
+        }
+
+        let mut full_at = self.buckets.entry(key.to_owned()).or_insert(now);
+        let next = (*full_at).max(now) + limit.interval;
+        let ahead = next.saturating_duration_since(now);
+        if ahead > limit.window() {
+            return Ok(Some(ahead - limit.window()));
+        }
+        *full_at = next;
+        Ok(None)
+    }
+}
+
+/// Buckets in the `rate_limits` table, shared by every replica.
+#[derive(Clone)]
+pub struct PgRateLimitStore {
+    db: Pool,
+    /// unix seconds of the last time full buckets were deleted
+    pruned_at: Arc<AtomicU64>,
+}
+
+impl Svc for PgRateLimitStore {}
+
+impl PgRateLimitStore {
+    pub fn new(db: Pool) -> Self {
+        Self {
+            db,
+            pruned_at: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Deletes the full buckets once a minute, whichever request comes first.
+    async fn prune(&self, conn: &mut diesel_async::AsyncPgConnection) -> anyhow::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        // `RunQueryDsl` has a `load` too
+        let last = AtomicU64::load(&self.pruned_at, Ordering::Relaxed);
+        if now < last + PG_PRUNE_EVERY_SECS
+            || self
+                .pruned_at
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return Ok(());
+        }
+        diesel::sql_query("DELETE FROM rate_limits WHERE tat < now()")
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(QueryableByName)]
+struct Wait {
+    #[diesel(sql_type = Double)]
+    secs: f64,
+}
+
+impl RateLimitStore for PgRateLimitStore {
+    async fn take(&self, key: &str, limit: Limit) -> anyhow::Result<Option<Duration>> {
+        let mut conn = self.db.get().await?;
+        self.prune(&mut conn).await?;
+
+        let interval = limit.interval.as_secs_f64();
+        let window = limit.window().as_secs_f64();
+        // the update only happens while within the window, so a row coming
+        // back means a token was taken
+        let taken = diesel::sql_query(
+            "INSERT INTO rate_limits AS r (key, tat) \
+             VALUES ($1, now() + make_interval(secs => $2)) \
+             ON CONFLICT (key) DO UPDATE \
+             SET tat = GREATEST(r.tat, now()) + make_interval(secs => $2) \
+             WHERE GREATEST(r.tat, now()) + make_interval(secs => $2) \
+                <= now() + make_interval(secs => $3) \
+             RETURNING 0::float8 AS secs",
+        )
+        .bind::<Text, _>(key)
+        .bind::<Double, _>(interval)
+        .bind::<Double, _>(window)
+        .get_result::<Wait>(&mut conn)
+        .await
+        .optional()?;
+        if taken.is_some() {
+            return Ok(None);
+        }
+
+        let wait = diesel::sql_query(
+            "SELECT EXTRACT(EPOCH FROM GREATEST(tat, now()) - now())::float8 + $2 - $3 AS secs \
+             FROM rate_limits WHERE key = $1",
+        )
+        .bind::<Text, _>(key)
+        .bind::<Double, _>(interval)
+        .bind::<Double, _>(window)
+        .get_result::<Wait>(&mut conn)
+        .await?;
+        Ok(Some(Duration::from_secs_f64(wait.secs.max(0.0))))
+    }
+}
+
+/// The store picked by `rate_limit.store` in the app config.
+#[derive(Clone)]
+pub enum AppRateLimitStore {
+    Memory(MemoryRateLimitStore),
+    Postgres(PgRateLimitStore),
+}
+
+impl Svc for AppRateLimitStore {}
+
+impl RateLimitStore for AppRateLimitStore {
+    async fn take(&self, key: &str, limit: Limit) -> anyhow::Result<Option<Duration>> {
+        match self {
+            Self::Memory(s) => s.take(key, limit).await,
+            Self::Postgres(s) => s.take(key, limit).await,
+        }
+    }
+}
+
+struct RoutePolicy {
+    method: Method,
+    path: String,
+    key: RateLimitKey,
+    limit: Limit,
+}
+
+/// The state of the `rate_limit` middleware.
+#[derive(Clone)]
+pub struct RateLimiter<Store = AppRateLimitStore> {
+    routes: Arc<[RoutePolicy]>,
+    store: Store,
+    trust_forwarded_for: bool,
+    max_body_bytes: usize,
+}
+
+impl RateLimiter {
+    pub fn from_cfg(cfg: &RateLimitCfg, db: Pool) -> anyhow::Result<Self> {
+        let store = match cfg.store {
+            RateLimitBackend::Memory => AppRateLimitStore::Memory(Default::default()),
+            RateLimitBackend::Postgres => AppRateLimitStore::Postgres(PgRateLimitStore::new(db)),
+        };
+        Self::new(cfg, store)
+    }
+}
+
+impl<Store: RateLimitStore> RateLimiter<Store> {
+    pub fn new(cfg: &RateLimitCfg, store: Store) -> anyhow::Result<Self> {
+        let routes = cfg
+            .routes
+            .iter()
+            .map(RoutePolicy::try_from)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            routes,
+            store,
+            trust_forwarded_for: cfg.trust_forwarded_for,
+            max_body_bytes: cfg.max_body_bytes,
+        })
+    }
+
+    /// The address the request came from.
+    fn client_ip(&self, req: &Request) -> Option<IpAddr> {
+        if self.trust_forwarded_for {
+            let forwarded = req
+                .headers()
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                // the entries before the one of our proxy come from the client
+                .next_back()
+                .and_then(|ip| ip.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+
+    /// The bucket of the request. Reading the `user_id` consumes the body,
+    /// so the request to pass on comes back with it.
+    async fn bucket_key(
+        &self,
+        route: &RoutePolicy,
+        req: Request,
+    ) -> Result<(String, Request), Response> {
+        let ip = self
+            .client_ip(&req)
+            .map_or_else(|| "unknown".to_owned(), |ip| ip.to_string());
+        // users and tokens are whatever the client says, so they stay with the
+        // address: nobody empties someone else's bucket from elsewhere. Made
+        // up ones still get their own, which is why the defaults go by the
+        // address alone
+        let (client, req) = match route.key {
+            RateLimitKey::Ip => (None, req),
+            RateLimitKey::User => match self.acting_user(req).await? {
+                (Some(user_id), req) => (Some(format!("user:{user_id}")), req),
+                (None, req) => (None, req),
+            },
+            RateLimitKey::Token => {
+                // the store only ever sees a hash of the token
+                let token = bearer_token(req.headers())
+                    .map(|token| format!("token:{:x}", Sha256::digest(token)));
+                (token, req)
+            }
+        };
+        let mut key = format!("{} {}|ip:{ip}", route.method, route.path);
+        if let Some(client) = client {
+            key = format!("{key}|{client}");
+        }
+        Ok((key, req))
+    }
+
+    /// The `user_id` in the query string or the form or JSON body.
+    async fn acting_user(&self, req: Request) -> Result<(Option<i32>, Request), Response> {
+        #[derive(Deserialize)]
+        struct Acting {
+            user_id: Option<i32>,
+        }
+
+        let query = req.uri().query().unwrap_or_default();
+        if let Ok(Acting { user_id: Some(id) }) = serde_urlencoded::from_str(query) {
+            return Ok((Some(id), req));
+        }
+
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        let small = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok()?.parse::<usize>().ok())
+            .is_some_and(|len| len <= self.max_body_bytes);
+        let is_form = content_type.starts_with("application/x-www-form-urlencoded");
+        let is_multipart = content_type.starts_with("multipart/form-data");
+        let is_json = content_type.starts_with("application/json");
+        if !small || !(is_form || is_multipart || is_json) {
+            return Ok((None, req));
+        }
+
+        let (parts, body) = req.into_parts();
+        let bytes = axum::body::to_bytes(body, self.max_body_bytes)
+            .await
+            .map_err(|e| AppError::validation(Message::text(e.to_string())).into_response())?;
+        let user_id = if is_form {
+            serde_urlencoded::from_bytes::<Acting>(&bytes)
+                .ok()
+                .and_then(|a| a.user_id)
+        } else if is_json {
+            serde_json::from_slice::<Acting>(&bytes)
+                .ok()
+                .and_then(|a| a.user_id)
+        } else {
+            let mut probe = Request::new(Body::from(bytes.clone()));
+            *probe.headers_mut() = parts.headers.clone();
+            multipart_user_id(probe).await
+        };
+        Ok((user_id, Request::from_parts(parts, Body::from(bytes))))
+    }
+}
+
+async fn multipart_user_id(req: Request) -> Option<i32> {
+    let mut multipart = Multipart::from_request(req, &()).await.ok()?;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() == Some("user_id") {
+            return field.text().await.ok()?.trim().parse().ok();
+        }
+    }
+    None
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+impl TryFrom<&RouteLimit> for RoutePolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(r: &RouteLimit) -> anyhow::Result<Self> {
+        if r.burst == 0 || r.per_minute == 0 {
+            bail!(
+                "rate limit of {} {} needs a burst and per_minute above 0",
+                r.method,
+                r.path
+            );
+        }
+        Ok(Self {
+            method: r
+                .method
+                .to_uppercase()
+                .parse()
+                .with_context(|| format!("rate limit method `{}`", r.method))?,
+            path: r.path.clone(),
+            key: r.key,
+            limit: Limit {
+                interval: Duration::from_secs(60) / r.per_minute,
+                burst: r.burst,
+            },
+        })
+    }
+}
+
+/// Lets requests to limited routes through while their bucket has a token.
+/// When the store fails the request goes through, a broken store should not
+/// take the site down.
+pub async fn rate_limit<Store: RateLimitStore>(
+    State(limiter): State<RateLimiter<Store>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = limiter
+        .routes
+        .iter()
+        .find(|r| r.method == req.method() && r.path == req.uri().path())
+    else {
+        return next.run(req).await;
+    };
+
+    let (key, req) = match limiter.bucket_key(route, req).await {
+        Ok(keyed) => keyed,
+        Err(res) => return res,
+    };
+    match limiter.store.take(&key, route.limit).await {
+        Ok(None) => next.run(req).await,
+        Ok(Some(wait)) => AppError::RateLimited { retry_after: wait }.into_response(),
+        Err(e) => {
+            error!(
+                key,
+                "rate limit store failed, letting the request through: {e:?}"
+            );
+            next.run(req).await
+        }
+    }
+}
+
+pub trait RateLimitExt<S> {
+    fn with_rate_limit<Store: RateLimitStore>(self, limiter: RateLimiter<Store>) -> Self;
+}
+
+impl<S> RateLimitExt<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// Add the limits of `limiter` to Router
+    fn with_rate_limit<Store: RateLimitStore>(self, limiter: RateLimiter<Store>) -> Router<S> {
+        self.layer(axum::middleware::from_fn_with_state(
+            limiter,
+            rate_limit::<Store>,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderValue, StatusCode};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn limiter(key: RateLimitKey) -> RateLimiter<MemoryRateLimitStore> {
+        let cfg = RateLimitCfg {
+            routes: vec![RouteLimit {
+                method: "post".to_owned(),
+                path: "/posts".to_owned(),
+                key,
+                burst: 2,
+                per_minute: 60,
+            }],
+            ..Default::default()
+        };
+        RateLimiter::new(&cfg, MemoryRateLimitStore::default()).unwrap()
+    }
+
+    fn post(content_type: &str, body: impl Into<String>) -> Request {
+        let body = body.into();
+        let mut req = Request::post("/posts")
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap();
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))));
+        req
+    }
+
+    async fn bucket(limiter: &RateLimiter<MemoryRateLimitStore>, req: Request) -> String {
+        let Ok((key, req)) = limiter.bucket_key(&limiter.routes[0], req).await else {
+            panic!("no bucket for the request");
+        };
+        // whatever was read of the body is passed on
+        let body = axum::body::to_bytes(req.into_body(), usize::MAX).await;
+        assert!(body.is_ok());
+        key
+    }
+
+    #[tokio::test]
+    async fn memory_store_allows_bursts_then_refills() {
+        let store = MemoryRateLimitStore::default();
+        let limit = Limit {
+            interval: Duration::from_millis(50),
+            burst: 3,
+        };
+        for _ in 0..3 {
+            assert_eq!(store.take("a", limit).await.unwrap(), None);
+        }
+        let wait = store.take("a", limit).await.unwrap().unwrap();
+        assert!(wait > Duration::ZERO && wait <= limit.interval, "{wait:?}");
+        // other buckets are not affected
+        assert_eq!(store.take("b", limit).await.unwrap(), None);
+
+        tokio::time::sleep(wait).await;
+        assert_eq!(store.take("a", limit).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn ip_keys_trust_forwarded_for_only_when_told_to() {
+        let mut limiter = limiter(RateLimitKey::Ip);
+        let forwarded = || {
+            let mut req = post("text/plain", "");
+            req.headers_mut().insert(
+                "x-forwarded-for",
+                HeaderValue::from_static("1.1.1.1, 2.2.2.2"),
+            );
+            req
+        };
+        assert_eq!(
+            bucket(&limiter, forwarded()).await,
+            "POST /posts|ip:10.0.0.1"
+        );
+        limiter.trust_forwarded_for = true;
+        assert_eq!(
+            bucket(&limiter, forwarded()).await,
+            "POST /posts|ip:2.2.2.2"
+        );
+    }
+
+    #[tokio::test]
+    async fn user_keys_find_the_user_id_in_query_or_body() {
+        let limiter = limiter(RateLimitKey::User);
+        let form = post(
+            "application/x-www-form-urlencoded",
+            "post_content=hi&user_id=7",
+        );
+        assert_eq!(
+            bucket(&limiter, form).await,
+            "POST /posts|ip:10.0.0.1|user:7"
+        );
+        let json = post(
+            "application/json",
+            r#"{"user_id": 8, "post_content": "hi"}"#,
+        );
+        assert_eq!(
+            bucket(&limiter, json).await,
+            "POST /posts|ip:10.0.0.1|user:8"
+        );
+        let multipart = post(
+            "multipart/form-data; boundary=X",
+            "--X\r\nContent-Disposition: form-data; name=\"user_id\"\r\n\r\n9\r\n--X--\r\n",
+        );
+        assert_eq!(
+            bucket(&limiter, multipart).await,
+            "POST /posts|ip:10.0.0.1|user:9"
+        );
+
+        let mut query = post("text/plain", "");
+        *query.uri_mut() = "/posts?user_id=6".parse().unwrap();
+        assert_eq!(
+            bucket(&limiter, query).await,
+            "POST /posts|ip:10.0.0.1|user:6"
+        );
+
+        // too big to read, or no user at all
+        let big = format!(
+            "user_id=7&post_content={}",
+            "x".repeat(limiter.max_body_bytes)
+        );
+        let big = post("application/x-www-form-urlencoded", big);
+        assert_eq!(bucket(&limiter, big).await, "POST /posts|ip:10.0.0.1");
+        let anonymous = post("application/x-www-form-urlencoded", "post_content=hi");
+        assert_eq!(bucket(&limiter, anonymous).await, "POST /posts|ip:10.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn token_keys_hash_the_bearer_token() {
+        let limiter = limiter(RateLimitKey::Token);
+        let mut req = post("application/json", "{}");
+        req.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        let key = bucket(&limiter, req).await;
+        assert_eq!(
+            key,
+            format!(
+                "POST /posts|ip:10.0.0.1|token:{:x}",
+                Sha256::digest("secret")
+            )
+        );
+        assert!(!key.contains("secret"));
+
+        let anonymous = post("application/json", "{}");
+        assert_eq!(bucket(&limiter, anonymous).await, "POST /posts|ip:10.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn posting_as_someone_else_takes_from_the_same_bucket() {
+        let limiter = RateLimiter::new(&RateLimitCfg::default(), MemoryRateLimitStore::default());
+        let app = Router::new()
+            .route("/posts", axum::routing::post(|| async { "posted" }))
+            .with_rate_limit(limiter.unwrap());
+        let mut statuses = vec![];
+        for user_id in 0..11 {
+            let req = post(
+                "application/x-www-form-urlencoded",
+                format!("post_content=hi&user_id={user_id}"),
+            );
+            statuses.push(app.clone().oneshot(req).await.unwrap().status());
+        }
+        assert!(
+            statuses[..10].iter().all(|s| *s == StatusCode::OK),
+            "{statuses:?}"
+        );
+        assert_eq!(statuses[10], StatusCode::TOO_MANY_REQUESTS);
+    }
+}
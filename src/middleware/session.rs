@@ -0,0 +1,154 @@
+//! Who a browser is signed in as. There are no passwords yet, so a browser
+//! is signed in as the user it creates, in a cookie signed with
+//! `server.session_key`: nobody can make one up for somebody else. Anything
+//! a user may only do as themselves, like moderating, goes by the `Session`
+//! and never by a `user_id` sent along with the request.
+use axum::Router;
+use axum::extract::{FromRequestParts, OptionalFromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, IntoResponseParts, Response, ResponseParts};
+use axum_extra::extract::SignedCookieJar;
+use axum_extra::extract::cookie::{Cookie, Key, SameSite};
+
+use crate::AppError;
+
+pub const COOKIE: &str = "session";
+
+/// The user the request is signed in as. Rejected with a 401 when there is
+/// none, `Option<Session>` for pages that work either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub user_id: i32,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Session {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Session>()
+            .copied()
+            .ok_or_else(|| AppError::unauthorized("error-signed-out"))
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for Session {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Session>().copied())
+    }
+}
+
+/// Signs the browser in as the user, returned next to the body of a
+/// response.
+#[derive(Debug, Clone, Copy)]
+pub struct SignIn(pub i32);
+
+impl IntoResponseParts for SignIn {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+/// Makes the `Session` of a validly signed cookie available to handlers and
+/// sets the cookie of a `SignIn`.
+pub async fn session(State(key): State<Key>, mut req: Request, next: Next) -> Response {
+    let jar = SignedCookieJar::from_headers(req.headers(), key);
+    // the jar only hands out cookies whose signature checks out
+    if let Some(user_id) = jar.get(COOKIE).and_then(|c| c.value().parse().ok()) {
+        req.extensions_mut().insert(Session { user_id });
+    }
+
+    let res = next.run(req).await;
+    let Some(SignIn(user_id)) = res.extensions().get::<SignIn>().copied() else {
+        return res;
+    };
+    let cookie = Cookie::build((COOKIE, user_id.to_string()))
+        .path("/")
+        .same_site(SameSite::Strict)
+        .http_only(true)
+        .permanent();
+    (jar.add(cookie), res).into_response()
+}
+
+pub trait SessionExt<S> {
+    fn with_session(self, key: Key) -> Self;
+}
+
+impl<S> SessionExt<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    /// Add sessions signed with `key` to Router
+    fn with_session(self, key: Key) -> Router<S> {
+        self.layer(axum::middleware::from_fn_with_state(key, session))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{StatusCode, header};
+    use axum::routing::{get, post};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(key: Key) -> Router {
+        Router::new()
+            .route(
+                "/me",
+                get(|s: Session| async move { s.user_id.to_string() }),
+            )
+            .route("/users", post(|| async { (SignIn(7), "created") }))
+            .with_session(key)
+    }
+
+    async fn me(app: Router, cookie: &str) -> StatusCode {
+        let req = Request::get("/me")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn creating_a_user_signs_the_browser_in() {
+        let app = app(Key::generate());
+        let res = app
+            .clone()
+            .oneshot(Request::post("/users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let set_cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+        assert!(set_cookie.contains("HttpOnly"), "{set_cookie}");
+        let cookie = set_cookie.split(';').next().unwrap();
+
+        assert_eq!(me(app, cookie).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn made_up_sessions_are_signed_out() {
+        // signed, but with another key
+        let res = app(Key::generate())
+            .oneshot(Request::post("/users").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let set_cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+        let cookie = set_cookie.split(';').next().unwrap();
+
+        let app = app(Key::generate());
+        assert_eq!(me(app.clone(), cookie).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(me(app.clone(), "").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            me(app, &format!("{COOKIE}=7")).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// An image attached to a post. The blobs are served under `/blobs/<key>`.
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Selectable, ToSchema)]
+#[diesel(table_name = crate::schema::post_attachments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Attachment {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub content_type: String,
+    pub byte_size: i32,
+    pub width: i32,
+    pub height: i32,
+    pub blob_key: String,
+    pub thumb_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An image already in the blob store, waiting for the post it belongs to.
+#[derive(Debug)]
+pub struct StoredImage {
+    pub id: Uuid,
+    pub content_type: String,
+    pub byte_size: i32,
+    pub width: i32,
+    pub height: i32,
+    pub blob_key: String,
+    pub thumb_key: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::post_attachments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewAttachment<'a> {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub content_type: &'a str,
+    pub byte_size: i32,
+    pub width: i32,
+    pub height: i32,
+    pub blob_key: &'a str,
+    pub thumb_key: &'a str,
+}
+
+impl<'a> NewAttachment<'a> {
+    pub fn new(post_id: Uuid, image: &'a StoredImage) -> Self {
+        Self {
+            id: image.id,
+            post_id,
+            content_type: &image.content_type,
+            byte_size: image.byte_size,
+            width: image.width,
+            height: image.height,
+            blob_key: &image.blob_key,
+            thumb_key: &image.thumb_key,
+        }
+    }
+}
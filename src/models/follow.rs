@@ -0,0 +1,9 @@
+use diesel::prelude::*;
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::follows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewFollow {
+    pub follower_id: i32,
+    pub followee_id: i32,
+}
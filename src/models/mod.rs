@@ -1,2 +1,119 @@
+pub mod attachment;
+pub mod follow;
+pub mod moderation;
+pub mod notification;
 pub mod post;
+pub mod reaction;
+pub mod revision;
 pub mod user;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::ToSchema;
+
+// the user a form is submitted on behalf of, e.g. who reacts or follows
+#[derive(Deserialize, Debug)]
+pub struct ActingUser {
+    pub user_id: i32,
+}
+
+/// An `ActingUser` for pages that also work for nobody in particular, e.g.
+/// before the acting user is filled in.
+#[derive(Deserialize, Debug)]
+pub struct MaybeActingUser {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub user_id: Option<i32>,
+}
+
+/// One page of a keyset paginated list. `next_cursor` is absent on the last
+/// page.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Ordering of list endpoints by `created_at`.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    #[default]
+    Newest,
+    Oldest,
+}
+
+impl std::str::FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "newest" => Ok(Self::Newest),
+            "oldest" => Ok(Self::Oldest),
+            other => Err(format!("unknown sort order `{other}`")),
+        }
+    }
+}
+
+/// A point in time from a form, in RFC 3339. A bare `datetime-local` value
+/// has no offset and could be any time at all, so the page sends the time
+/// with the browser's offset instead (see `data-zoned` in `index.js`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormDateTime(pub DateTime<Utc>);
+
+impl std::str::FromStr for FormDateTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match DateTime::parse_from_rfc3339(s) {
+            Ok(t) => Ok(Self(t.to_utc())),
+            Err(_) if is_naive(s) => Err(format!("date and time `{s}` needs an offset")),
+            Err(_) => Err(format!("invalid date and time `{s}`")),
+        }
+    }
+}
+
+// what a `datetime-local` `<input>` sends
+fn is_naive(s: &str) -> bool {
+    ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .any(|f| NaiveDateTime::parse_from_str(s, f).is_ok())
+}
+
+/// Treats an empty query/form value (e.g. an untouched `<input>`) as absent
+/// instead of failing to parse it.
+pub fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let opt = Option::<String>::deserialize(de)?;
+    match opt.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(s) => s.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn form_date_times_keep_their_offset() {
+        let at: FormDateTime = "2026-03-01T09:30:00+01:00".parse().unwrap();
+        assert_eq!(at.0, Utc.with_ymd_and_hms(2026, 3, 1, 8, 30, 0).unwrap());
+        let at: FormDateTime = "2026-03-01T08:30:00.000Z".parse().unwrap();
+        assert_eq!(at.0, Utc.with_ymd_and_hms(2026, 3, 1, 8, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn form_date_times_without_offset_are_rejected() {
+        for naive in ["2026-03-01T09:30", "2026-03-01T09:30:00"] {
+            let err = naive.parse::<FormDateTime>().unwrap_err();
+            assert!(err.contains("needs an offset"), "{err}");
+        }
+        assert!("tomorrow".parse::<FormDateTime>().is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::empty_string_as_none;
+use super::post::Post;
+
+/// What a moderator did. Stored by its snake_case name, which the
+/// `moderation_actions.kind` check constraint mirrors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationKind {
+    /// took `post` out of every list and feed
+    Hide,
+    /// deleted `post` and its replies
+    Delete,
+    /// kept `target_user` from posting until `suspended_until`
+    Suspend,
+    /// closed the reports of `post` without acting on it
+    Dismiss,
+    /// published `post` held by a content filter
+    Approve,
+}
+
+impl ModerationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationKind::Hide => "hide",
+            ModerationKind::Delete => "delete",
+            ModerationKind::Suspend => "suspend",
+            ModerationKind::Dismiss => "dismiss",
+            ModerationKind::Approve => "approve",
+        }
+    }
+}
+
+impl std::str::FromStr for ModerationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hide" => Ok(ModerationKind::Hide),
+            "delete" => Ok(ModerationKind::Delete),
+            "suspend" => Ok(ModerationKind::Suspend),
+            "dismiss" => Ok(ModerationKind::Dismiss),
+            "approve" => Ok(ModerationKind::Approve),
+            other => Err(format!("unknown moderation kind `{other}`")),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for ModerationKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ModerationKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+// the input to our `report_post` handler
+#[derive(Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::post_reports)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ReportPost {
+    #[serde(rename = "user_id")]
+    pub reporter_id: i32,
+    pub post_id: Uuid,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::post_reports)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Report {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub reporter_id: i32,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// A post in the moderation queue with its open reports, oldest first.
+#[derive(Serialize, Debug)]
+pub struct ReportedPost {
+    #[serde(flatten)]
+    pub post: Post,
+    pub reports: Vec<Report>,
+}
+
+// the input to the handlers acting on a post from the queue
+#[derive(Deserialize, Debug)]
+pub struct ModeratePost {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub reason: Option<String>,
+}
+
+// the input to our `suspend_user` handler
+#[derive(Deserialize, Debug)]
+pub struct SuspendUser {
+    pub hours: u32,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = crate::schema::moderation_actions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewModerationAction<'a> {
+    pub moderator_id: i32,
+    pub kind: ModerationKind,
+    pub post_id: Option<Uuid>,
+    pub target_user_id: Option<i32>,
+    pub reason: Option<&'a str>,
+    pub suspended_until: Option<DateTime<Utc>>,
+}
+
+/// An entry of the audit log. The users are `None` once deleted.
+#[derive(Serialize, Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::moderation_actions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ModerationAction {
+    pub id: Uuid,
+    pub moderator_id: Option<i32>,
+    pub kind: ModerationKind,
+    pub post_id: Option<Uuid>,
+    pub target_user_id: Option<i32>,
+    pub reason: Option<String>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
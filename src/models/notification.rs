@@ -0,0 +1,95 @@
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Why a user is notified. Stored by its snake_case name, which the
+/// `notifications.kind` check constraint mirrors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// `actor` mentioned the user in `post`
+    Mention,
+    /// `actor` replied to the user's post with `post`
+    Reply,
+    /// `actor` reacted to the user's `post`
+    Reaction,
+    /// `actor` started following the user
+    Follow,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Mention => "mention",
+            NotificationKind::Reply => "reply",
+            NotificationKind::Reaction => "reaction",
+            NotificationKind::Follow => "follow",
+        }
+    }
+}
+
+impl std::str::FromStr for NotificationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mention" => Ok(NotificationKind::Mention),
+            "reply" => Ok(NotificationKind::Reply),
+            "reaction" => Ok(NotificationKind::Reaction),
+            "follow" => Ok(NotificationKind::Follow),
+            other => Err(format!("unknown notification kind `{other}`")),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for NotificationKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for NotificationKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+#[derive(Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = crate::schema::notifications)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewNotification {
+    pub user_id: i32,
+    pub kind: NotificationKind,
+    pub actor_id: i32,
+    pub post_id: Option<Uuid>,
+}
+
+/// A notification with what is needed to render it.
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable)]
+pub struct NotificationView {
+    pub id: Uuid,
+    pub user_id: i32,
+    pub kind: NotificationKind,
+    pub actor_id: i32,
+    pub actor_email: String,
+    pub post_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+// the query string of `GET /notifications`
+#[derive(Deserialize, Debug)]
+pub struct ListNotifications {
+    pub user_id: i32,
+}
@@ -1,21 +1,361 @@
+use std::collections::HashMap;
+
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
 use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-#[derive(Deserialize, Insertable)]
+use super::attachment::Attachment;
+use super::reaction::{Reaction, ReactionCounts};
+use super::{FormDateTime, Sort, empty_string_as_none};
+use crate::i18n::Message;
+use crate::templating::markdown;
+
+/// Where a post is in its life. Only `Published` posts are visible to
+/// anyone but their author.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum PostStatus {
+    Draft,
+    /// goes out by itself at `publish_at`
+    Scheduled,
+    Published,
+    /// stopped by a content filter, see `services::content_filter`; waits in
+    /// the moderation queue for approval
+    Held,
+}
+
+impl PostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Published => "published",
+            PostStatus::Held => "held",
+        }
+    }
+}
+
+impl std::str::FromStr for PostStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(PostStatus::Draft),
+            "scheduled" => Ok(PostStatus::Scheduled),
+            "published" => Ok(PostStatus::Published),
+            "held" => Ok(PostStatus::Held),
+            other => Err(format!("unknown post status `{other}`")),
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for PostStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for PostStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+/// Which button the author pressed under the post form.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PostIntent {
+    #[default]
+    Publish,
+    Draft,
+    Schedule,
+}
+
+// the input to our `create_post` and `update_draft` handlers, next to any
+// attachments
+#[derive(Deserialize, ToSchema)]
+pub struct CreatePost {
+    pub user_id: i32,
+    pub post_content: String,
+    #[serde(default)]
+    pub intent: PostIntent,
+    /// required to schedule
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub publish_at: Option<FormDateTime>,
+}
+
+impl CreatePost {
+    /// The status the post ends up in, and when it is to be published if
+    /// that is later. Scheduling needs a `publish_at` in the future.
+    pub fn status(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<(PostStatus, Option<DateTime<Utc>>), Message> {
+        match (self.intent, self.publish_at) {
+            (PostIntent::Publish, _) => Ok((PostStatus::Published, None)),
+            (PostIntent::Draft, _) => Ok((PostStatus::Draft, None)),
+            (PostIntent::Schedule, Some(FormDateTime(at))) if at > now => {
+                Ok((PostStatus::Scheduled, Some(at)))
+            }
+            (PostIntent::Schedule, Some(_)) => Err("error-publish-at-past".into()),
+            (PostIntent::Schedule, None) => Err("error-publish-at-missing".into()),
+        }
+    }
+}
+
+/// A post as stored: its markdown source and the HTML rendered from it.
+#[derive(Insertable)]
 #[diesel(table_name = crate::schema::posts)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-pub struct CreatePost {
-    user_id: i32,
-    post_content: String,
+pub struct NewPost<'a> {
+    pub user_id: i32,
+    pub post_content: &'a str,
+    pub post_content_html: String,
+    pub status: PostStatus,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub held_reason: Option<String>,
 }
 
-#[derive(Serialize, Debug, Queryable, Selectable)]
+impl<'a> NewPost<'a> {
+    pub fn new(p: &'a CreatePost, status: PostStatus, publish_at: Option<DateTime<Utc>>) -> Self {
+        NewPost {
+            user_id: p.user_id,
+            post_content: &p.post_content,
+            post_content_html: markdown::render(&p.post_content),
+            status,
+            publish_at,
+            held_reason: None,
+        }
+    }
+
+    /// Keeps the post back for a moderator instead of publishing or
+    /// scheduling it.
+    pub fn hold(self, reason: String) -> Self {
+        NewPost {
+            status: PostStatus::Held,
+            publish_at: None,
+            held_reason: Some(reason),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Selectable, ToSchema)]
 #[diesel(table_name = crate::schema::posts)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Post {
-    id: Uuid,
-    user_id: i32,
-    post_content: String,
-    tags: Vec<Option<String>>,
+    pub id: Uuid,
+    pub user_id: i32,
+    pub post_content: String,
+    /// `None` for posts written before bodies were rendered; the `post_html`
+    /// template filter renders those on the fly.
+    pub post_content_html: Option<String>,
+    pub tags: Vec<Option<String>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub parent_id: Option<Uuid>,
+    pub root_id: Option<Uuid>,
+    pub reply_count: i32,
+    pub status: PostStatus,
+    pub publish_at: Option<DateTime<Utc>>,
+    /// when the content last changed after publishing, see `post_revisions`
+    pub edited_at: Option<DateTime<Utc>>,
+    /// set by a moderator; hidden posts are left out like unpublished ones
+    pub hidden_at: Option<DateTime<Utc>>,
+    /// why a content filter held the post for review
+    pub held_reason: Option<String>,
+}
+
+// the input to our `create_reply` handler
+#[derive(Deserialize)]
+pub struct CreateReply {
+    pub user_id: i32,
+    pub post_content: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::posts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewReply<'a> {
+    pub user_id: i32,
+    pub post_content: &'a str,
+    pub post_content_html: String,
+    pub parent_id: Uuid,
+    pub root_id: Uuid,
+    pub status: PostStatus,
+    pub held_reason: Option<String>,
+}
+
+/// A post and, recursively, every reply below it.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PostThreadNode {
+    #[serde(flatten)]
+    pub post: Post,
+    #[schema(value_type = BTreeMap<Reaction, i64>)]
+    pub reactions: ReactionCounts,
+    pub attachments: Vec<Attachment>,
+    #[schema(no_recursion)]
+    pub replies: Vec<PostThreadNode>,
+}
+
+impl PostThreadNode {
+    /// Assembles the tree below `root` out of the flat list of posts in its
+    /// thread. Replies keep the order they come in.
+    pub fn build(
+        root: Post,
+        thread: Vec<Post>,
+        mut reactions: HashMap<Uuid, ReactionCounts>,
+        mut attachments: HashMap<Uuid, Vec<Attachment>>,
+    ) -> Self {
+        let mut children: HashMap<Uuid, Vec<Post>> = HashMap::new();
+        for p in thread {
+            if let Some(parent) = p.parent_id {
+                children.entry(parent).or_default().push(p);
+            }
+        }
+
+        fn attach(
+            post: Post,
+            children: &mut HashMap<Uuid, Vec<Post>>,
+            reactions: &mut HashMap<Uuid, ReactionCounts>,
+            attachments: &mut HashMap<Uuid, Vec<Attachment>>,
+        ) -> PostThreadNode {
+            let replies = children
+                .remove(&post.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| attach(p, children, reactions, attachments))
+                .collect();
+            PostThreadNode {
+                reactions: reactions.remove(&post.id).unwrap_or_default(),
+                attachments: attachments.remove(&post.id).unwrap_or_default(),
+                post,
+                replies,
+            }
+        }
+
+        attach(root, &mut children, &mut reactions, &mut attachments)
+    }
+}
+
+// the query string of `GET /posts/search`
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchPosts {
+    #[serde(default)]
+    pub q: String,
+    /// comma separated, every tag must be present on the post
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub tags: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub author: Option<i32>,
+    /// ranks by relevance when absent
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub sort: Option<Sort>,
+    /// only posts created at or after this instant (RFC 3339)
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub since: Option<DateTime<Utc>>,
+    /// only posts created before this instant (RFC 3339)
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl SearchPosts {
+    pub fn tags(&self) -> Vec<Option<String>> {
+        self.tags.as_deref().map(parse_tags).unwrap_or_default()
+    }
+}
+
+/// Splits a comma separated list of tags as typed into a form.
+pub fn parse_tags(tags: &str) -> Vec<Option<String>> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| Some(t.to_owned()))
+        .collect()
+}
+
+/// A post with its reaction counters and attachments, as shown in lists.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PostCard {
+    #[serde(flatten)]
+    pub post: Post,
+    #[schema(value_type = BTreeMap<Reaction, i64>)]
+    pub reactions: ReactionCounts,
+    pub attachments: Vec<Attachment>,
+}
+
+/// Position in a list ordered by `(created_at, id)`, of posts unless `Id`
+/// says otherwise. Travels as `<created_at as unix micros>_<id>` so it stays
+/// URL safe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedCursor<Id = Uuid> {
+    pub created_at: DateTime<Utc>,
+    pub id: Id,
+}
+
+impl From<&Post> for FeedCursor {
+    fn from(post: &Post) -> Self {
+        Self {
+            created_at: post.created_at,
+            id: post.id,
+        }
+    }
+}
+
+impl<Id: std::fmt::Display> std::fmt::Display for FeedCursor<Id> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.created_at.timestamp_micros(), self.id)
+    }
+}
+
+impl<Id: std::str::FromStr> std::str::FromStr for FeedCursor<Id> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid cursor `{s}`");
+        let (micros, id) = s.split_once('_').ok_or_else(invalid)?;
+        let created_at = micros
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(invalid)?;
+        let id = id.parse().map_err(|_| invalid())?;
+        Ok(Self { created_at, id })
+    }
+}
+
+// the query string of `GET /posts/feed`
+#[derive(Deserialize, Debug)]
+pub struct HomeFeed {
+    pub user_id: i32,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub cursor: Option<FeedCursor>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PostSearchHit {
+    #[serde(flatten)]
+    pub post: Post,
+    #[schema(value_type = BTreeMap<Reaction, i64>)]
+    pub reactions: ReactionCounts,
+    pub attachments: Vec<Attachment>,
+    pub rank: f32,
+    /// html escaped `post_content` excerpt with the matches wrapped in `<mark>`
+    pub headline: String,
 }
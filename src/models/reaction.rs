@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The fixed set of reactions a post can receive. Stored by its snake_case
+/// name, which the `post_reactions.reaction` check constraint mirrors.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    ToSchema,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum Reaction {
+    Like,
+    Love,
+    Laugh,
+    Wow,
+    Sad,
+}
+
+impl Reaction {
+    pub const ALL: [Reaction; 5] = [
+        Reaction::Like,
+        Reaction::Love,
+        Reaction::Laugh,
+        Reaction::Wow,
+        Reaction::Sad,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reaction::Like => "like",
+            Reaction::Love => "love",
+            Reaction::Laugh => "laugh",
+            Reaction::Wow => "wow",
+            Reaction::Sad => "sad",
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Reaction::Like => "👍",
+            Reaction::Love => "❤️",
+            Reaction::Laugh => "😂",
+            Reaction::Wow => "😮",
+            Reaction::Sad => "😢",
+        }
+    }
+}
+
+impl std::str::FromStr for Reaction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Reaction::ALL
+            .into_iter()
+            .find(|r| r.as_str() == s)
+            .ok_or_else(|| format!("unknown reaction `{s}`"))
+    }
+}
+
+impl ToSql<Text, Pg> for Reaction {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for Reaction {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+/// How often each reaction was given to a post. Reactions nobody gave are
+/// absent.
+pub type ReactionCounts = BTreeMap<Reaction, i64>;
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::post_reactions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewPostReaction {
+    pub post_id: Uuid,
+    pub user_id: i32,
+    pub reaction: Reaction,
+}
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use uuid::Uuid;
+
+use super::empty_string_as_none;
+use super::post::Post;
+use crate::templating::markdown;
+
+// the input to our `edit_post` handler
+#[derive(Deserialize)]
+pub struct EditPost {
+    pub user_id: i32,
+    pub post_content: String,
+    /// comma separated
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub tags: Option<String>,
+}
+
+/// New content for a published post. What it replaces is kept as a
+/// revision.
+pub struct PostEdit<'a> {
+    pub edited_by: i32,
+    pub post_content: &'a str,
+    pub post_content_html: String,
+    pub tags: Vec<Option<String>>,
+    pub held_reason: Option<String>,
+}
+
+impl<'a> PostEdit<'a> {
+    pub fn new(edited_by: i32, post_content: &'a str, tags: Vec<Option<String>>) -> Self {
+        Self {
+            edited_by,
+            post_content,
+            post_content_html: markdown::render(post_content),
+            tags,
+            held_reason: None,
+        }
+    }
+
+    /// Takes the post back to the moderators along with the edit.
+    pub fn hold(self, reason: String) -> Self {
+        Self {
+            held_reason: Some(reason),
+            ..self
+        }
+    }
+}
+
+/// A post as it was before `edited_by` changed it at `created_at`.
+#[derive(Serialize, Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::post_revisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PostRevision {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub post_content: String,
+    pub tags: Vec<Option<String>>,
+    pub edited_by: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::post_revisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewPostRevision<'a> {
+    pub post_id: Uuid,
+    pub post_content: &'a str,
+    pub tags: &'a [Option<String>],
+    pub edited_by: i32,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A run of words that stayed, came in or went away between two versions.
+#[derive(Serialize, Debug)]
+pub struct DiffSpan {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// A word level diff from `old` to `new`.
+pub fn diff(old: &str, new: &str) -> Vec<DiffSpan> {
+    let mut spans: Vec<DiffSpan> = Vec::new();
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let op = match change.tag() {
+            ChangeTag::Equal => DiffOp::Equal,
+            ChangeTag::Insert => DiffOp::Insert,
+            ChangeTag::Delete => DiffOp::Delete,
+        };
+        match spans.last_mut() {
+            Some(last) if last.op == op => last.text.push_str(change.value()),
+            _ => spans.push(DiffSpan {
+                op,
+                text: change.value().to_owned(),
+            }),
+        }
+    }
+    spans
+}
+
+/// A revision and what the edit that replaced it changed.
+#[derive(Serialize, Debug)]
+pub struct RevisionView {
+    #[serde(flatten)]
+    pub revision: PostRevision,
+    pub diff: Vec<DiffSpan>,
+    pub tags_after: Vec<Option<String>>,
+}
+
+impl RevisionView {
+    /// Pairs every revision, oldest first as stored, with the version that
+    /// followed it; the newest one with the current `post`. Newest first.
+    pub fn history(revisions: Vec<PostRevision>, post: &Post) -> Vec<Self> {
+        let mut next = (post.post_content.clone(), post.tags.clone());
+        revisions
+            .into_iter()
+            .rev()
+            .map(|revision| {
+                let after = std::mem::replace(
+                    &mut next,
+                    (revision.post_content.clone(), revision.tags.clone()),
+                );
+                Self {
+                    diff: diff(&revision.post_content, &after.0),
+                    tags_after: after.1,
+                    revision,
+                }
+            })
+            .collect()
+    }
+}
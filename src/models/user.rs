@@ -1,12 +1,20 @@
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::post::FeedCursor;
+use super::{Sort, empty_string_as_none};
 
 // the input to our `create_user` handler
-#[derive(Deserialize, Insertable)]
+#[derive(Deserialize, Insertable, ToSchema)]
 #[diesel(table_name = crate::schema::users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct CreateUser {
     pub email: String,
+    /// lets others `@handle` the user instead of `@email`
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub handle: Option<String>,
 }
 
 // the output to our `create_user` handler
@@ -16,4 +24,56 @@ pub struct CreateUser {
 pub struct User {
     pub id: i32,
     pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub handle: Option<String>,
+    /// see `services::avatars`, `None` until the user uploads one
+    pub avatar_hash: Option<String>,
+    /// may restore revisions of posts; granted in the database for now
+    pub is_moderator: bool,
+    /// may not post before then, see `services::moderation`
+    pub suspended_until: Option<DateTime<Utc>>,
+}
+
+impl From<&User> for FeedCursor<i32> {
+    fn from(user: &User) -> Self {
+        Self {
+            created_at: user.created_at,
+            id: user.id,
+        }
+    }
+}
+
+/// What anyone may see of a user, e.g. through `/api/v1/users`.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PublicUser {
+    pub id: i32,
+    pub handle: Option<String>,
+    /// see `services::avatars`, `None` until the user uploads one
+    pub avatar_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<User> for PublicUser {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            handle: user.handle,
+            avatar_hash: user.avatar_hash,
+            created_at: user.created_at,
+        }
+    }
+}
+
+// the query string of `GET /users`
+#[derive(Deserialize, Debug, Default)]
+pub struct ListUsers {
+    #[serde(default)]
+    pub sort: Sort,
+    /// only users created at or after this instant (RFC 3339)
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub since: Option<DateTime<Utc>>,
+    /// only users created before this instant (RFC 3339)
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub until: Option<DateTime<Utc>>,
 }
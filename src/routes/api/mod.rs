@@ -0,0 +1,74 @@
+//! The JSON API for clients other than the htmx pages, e.g. the mobile apps.
+//! Each version lives in its own module and stays as it is once released.
+//! Errors come as `application/problem+json`, see `middleware::errors`.
+//!
+//! The OpenAPI document is generated from the handlers of every version and
+//! served at `/api/openapi.json`; `openapi.json` next to this file is the
+//! committed copy clients are built against. After changing the API, update
+//! it with `UPDATE_OPENAPI=1 cargo test openapi`.
+use axum::Router;
+use axum::routing::get;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+use crate::config::Env;
+
+pub mod v1;
+
+const SPEC_PATH: &str = "/api/openapi.json";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "rust-fullstack API",
+        description = "For clients other than the htmx pages. Errors come as `application/problem+json`.",
+    ),
+    nest((path = "/api/v1", api = v1::ApiDoc)),
+)]
+struct ApiDoc;
+
+fn spec() -> utoipa::openapi::OpenApi {
+    let mut spec = ApiDoc::openapi();
+    // taken from Cargo.toml, which has none
+    spec.info.license = None;
+    spec
+}
+
+async fn openapi_json() -> axum::Json<utoipa::openapi::OpenApi> {
+    axum::Json(spec())
+}
+
+/// The OpenAPI document, and Swagger UI at `/api/docs` in development.
+pub fn docs_router<S>(env: &Env) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let router = Router::new().route(SPEC_PATH, get(openapi_json));
+    if *env == Env::Development {
+        router.merge(SwaggerUi::new("/api/docs").config(Config::new([SPEC_PATH])))
+    } else {
+        router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::spec;
+
+    const COMMITTED: &str = include_str!("openapi.json");
+
+    #[test]
+    fn openapi_spec_is_up_to_date() {
+        let generated = spec().to_pretty_json().unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/routes/api/openapi.json");
+            std::fs::write(path, &generated).unwrap();
+            return;
+        }
+        assert!(
+            generated == COMMITTED,
+            "src/routes/api/openapi.json is out of date, \
+             run `UPDATE_OPENAPI=1 cargo test openapi` and commit the result"
+        );
+    }
+}
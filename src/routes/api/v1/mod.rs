@@ -0,0 +1,63 @@
+//! `/api/v1`. Bodies are read with `custom_json_extractor::Json`, lists come
+//! in a `models::Page` envelope: follow `next_cursor` with `?cursor=` until
+//! it is absent.
+use axum::Router;
+use serde::Deserialize;
+use utoipa::OpenApi;
+
+use crate::services::content_filter::ContentFilterChain;
+use crate::services::moderation::ModerationServiceDb;
+use crate::services::notifications::NotificationServiceDb;
+use crate::services::posts::PostServiceDb;
+use crate::services::users::UserServiceDb;
+
+mod posts;
+mod users;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+
+type ApiRouteState = (
+    UserServiceDb,
+    PostServiceDb,
+    ModerationServiceDb,
+    NotificationServiceDb,
+    ContentFilterChain,
+    deadpool_lapin::Pool,
+);
+
+/// `?limit=` of list endpoints, clamped to `1..=MAX_PAGE_SIZE`.
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(transparent)]
+struct PageSize(Option<i64>);
+
+impl PageSize {
+    fn get(self) -> i64 {
+        self.0.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        users::list_users,
+        users::create_user,
+        users::get_user,
+        posts::create_post,
+        posts::feed,
+        posts::search,
+        posts::get_post,
+        posts::thread,
+    ),
+    tags(
+        (name = "users"),
+        (name = "posts", description = "Posts and their threads, reactions and attachments"),
+    ),
+)]
+pub struct ApiDoc;
+
+pub fn router() -> Router<ApiRouteState> {
+    Router::new()
+        .nest("/users", users::router())
+        .nest("/posts", posts::router())
+}
@@ -0,0 +1,216 @@
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response,
+    routing::get,
+};
+use chrono::Utc;
+use macros::ert;
+use serde::Deserialize;
+use tracing::error;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use super::{ApiRouteState, PageSize};
+use crate::AppError;
+use crate::middleware::custom_json_extractor::Json;
+use crate::middleware::errors::Problem;
+use crate::models::Page;
+use crate::models::empty_string_as_none;
+use crate::models::post::{
+    CreatePost, FeedCursor, NewPost, PostCard, PostSearchHit, PostStatus, PostThreadNode,
+    SearchPosts,
+};
+use crate::routes::{moderation, posts};
+use crate::services::announce;
+use crate::services::content_filter::Candidate;
+use crate::services::posts::PostService;
+
+const SEARCH_LIMIT: i64 = 50;
+
+// the query string of `GET /api/v1/posts/feed`
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FeedParams {
+    user_id: i32,
+    /// `next_cursor` of the previous page
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    #[param(value_type = Option<String>)]
+    cursor: Option<FeedCursor>,
+    #[serde(default)]
+    #[param(value_type = Option<i64>, minimum = 1, maximum = 100)]
+    limit: PageSize,
+}
+
+/// Publishes, schedules or saves a post like the form on the start page,
+/// without attachments.
+#[utoipa::path(
+    post,
+    path = "/posts",
+    tag = "posts",
+    request_body = CreatePost,
+    responses(
+        (status = 201, description = "Created, possibly held for review", body = PostCard),
+        (status = 403, description = "The user is suspended", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid, or rejected by a content filter", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn create_post(
+    State((_, post_svc, mod_svc, notify_svc, filters, rmq_conn_pool)): State<ApiRouteState>,
+    Json(f): Json<CreatePost>,
+) -> response::Result<(StatusCode, axum::Json<PostCard>)> {
+    moderation::ensure_can_post(&mod_svc, f.user_id).await?;
+    let (status, publish_at) = f.status(Utc::now()).map_err(AppError::Validation)?;
+    let held = if status == PostStatus::Draft {
+        None
+    } else {
+        let candidate = Candidate {
+            user_id: f.user_id,
+            content: &f.post_content,
+            post_id: None,
+        };
+        posts::screen(&filters, &candidate).await?
+    };
+
+    let mut new = NewPost::new(&f, status, publish_at);
+    if let Some(reason) = held {
+        new = new.hold(reason);
+    }
+    let (post, attachments) = post_svc
+        .create_post(&new, &[])
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    if post.status == PostStatus::Published {
+        announce::announce_post(&rmq_conn_pool, &notify_svc, &post, attachments.clone())
+            .await
+            .map_err(AppError::from)?;
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        axum::Json(PostCard {
+            post,
+            reactions: Default::default(),
+            attachments,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}",
+    tag = "posts",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = PostCard),
+        (status = 404, body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn get_post(
+    State((_, post_svc, ..)): State<ApiRouteState>,
+    Path(post_id): Path<Uuid>,
+) -> response::Result<axum::Json<PostCard>> {
+    let Some(post) = post_svc.get_post(post_id).await.map_err(AppError::from)? else {
+        return Err(AppError::not_found("error-post-not-found").into());
+    };
+    let reactions = post_svc
+        .reaction_counts(&[post.id])
+        .await
+        .map_err(AppError::from)?
+        .remove(&post.id)
+        .unwrap_or_default();
+    let attachments = post_svc
+        .attachments(&[post.id])
+        .await
+        .map_err(AppError::from)?
+        .remove(&post.id)
+        .unwrap_or_default();
+    Ok(axum::Json(PostCard {
+        post,
+        reactions,
+        attachments,
+    }))
+}
+
+/// The post and every reply below it.
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/thread",
+    tag = "posts",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = PostThreadNode),
+        (status = 404, body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn thread(
+    State((_, post_svc, ..)): State<ApiRouteState>,
+    Path(post_id): Path<Uuid>,
+) -> response::Result<axum::Json<PostThreadNode>> {
+    Ok(axum::Json(posts::load_thread(&post_svc, post_id).await?))
+}
+
+/// The home feed of `user_id`, newest first.
+#[utoipa::path(
+    get,
+    path = "/posts/feed",
+    tag = "posts",
+    params(FeedParams),
+    responses(
+        (status = 200, body = Page<PostCard>),
+        (status = 422, body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn feed(
+    State((_, post_svc, ..)): State<ApiRouteState>,
+    Query(params): Query<FeedParams>,
+) -> response::Result<axum::Json<Page<PostCard>>> {
+    let page = post_svc
+        .home_feed(params.user_id, params.cursor, params.limit.get())
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(axum::Json(page))
+}
+
+/// The best `SEARCH_LIMIT` hits, in a single page.
+#[utoipa::path(
+    get,
+    path = "/posts/search",
+    tag = "posts",
+    params(SearchPosts),
+    responses(
+        (status = 200, description = "The best 50 hits", body = Page<PostSearchHit>),
+        (status = 422, body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn search(
+    State((_, post_svc, ..)): State<ApiRouteState>,
+    Query(params): Query<SearchPosts>,
+) -> response::Result<axum::Json<Page<PostSearchHit>>> {
+    let hits = post_svc
+        .search(&params, SEARCH_LIMIT)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(axum::Json(Page {
+        items: hits,
+        next_cursor: None,
+    }))
+}
+
+pub fn router() -> Router<ApiRouteState> {
+    Router::new()
+        .route("/", axum::routing::post(create_post))
+        .route("/feed", get(feed))
+        .route("/search", get(search))
+        .route("/{id}", get(get_post))
+        .route("/{id}/thread", get(thread))
+}
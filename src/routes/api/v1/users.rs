@@ -0,0 +1,133 @@
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response,
+    routing::get,
+};
+use chrono::{DateTime, Utc};
+use macros::ert;
+use serde::Deserialize;
+use tracing::error;
+use utoipa::IntoParams;
+
+use super::{ApiRouteState, PageSize};
+use crate::AppError;
+use crate::middleware::custom_json_extractor::Json;
+use crate::middleware::errors::Problem;
+use crate::models::empty_string_as_none;
+use crate::models::post::FeedCursor;
+use crate::models::user::{CreateUser, ListUsers, PublicUser};
+use crate::models::{Page, Sort};
+use crate::services::users::UserService;
+
+// the query string of `GET /api/v1/users`
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListUsersParams {
+    #[serde(default)]
+    #[param(inline)]
+    sort: Sort,
+    /// only users created at or after this instant (RFC 3339)
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    since: Option<DateTime<Utc>>,
+    /// only users created before this instant (RFC 3339)
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    until: Option<DateTime<Utc>>,
+    /// `next_cursor` of the previous page
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    #[param(value_type = Option<String>)]
+    cursor: Option<FeedCursor<i32>>,
+    #[serde(default)]
+    #[param(value_type = Option<i64>, minimum = 1, maximum = 100)]
+    limit: PageSize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    params(ListUsersParams),
+    responses(
+        (status = 200, body = Page<PublicUser>),
+        (status = 422, body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn list_users(
+    State((user_svc, ..)): State<ApiRouteState>,
+    Query(params): Query<ListUsersParams>,
+) -> response::Result<axum::Json<Page<PublicUser>>> {
+    let limit = params.limit.get();
+    let list = ListUsers {
+        sort: params.sort,
+        since: params.since,
+        until: params.until,
+    };
+    let users = user_svc
+        .get_users(&list, params.cursor, limit)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+
+    let next_cursor = (users.len() as i64 == limit)
+        .then(|| users.last().map(|u| FeedCursor::from(u).to_string()))
+        .flatten();
+    Ok(axum::Json(Page {
+        items: users.into_iter().map(PublicUser::from).collect(),
+        next_cursor,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users",
+    tag = "users",
+    request_body = CreateUser,
+    responses(
+        (status = 201, body = PublicUser),
+        (status = 409, description = "The email or handle is taken", body = Problem, content_type = "application/problem+json"),
+        (status = 422, body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn create_user(
+    State((user_svc, ..)): State<ApiRouteState>,
+    Json(payload): Json<CreateUser>,
+) -> response::Result<(StatusCode, axum::Json<PublicUser>)> {
+    if payload.email.is_empty() {
+        return Err(AppError::validation("error-email-invalid").into());
+    }
+    let user = user_svc
+        .create_user(&payload)
+        .await
+        .map_err(AppError::from)?;
+    Ok((StatusCode::CREATED, axum::Json(user.into())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = PublicUser),
+        (status = 404, body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+async fn get_user(
+    State((user_svc, ..)): State<ApiRouteState>,
+    Path(user_id): Path<i32>,
+) -> response::Result<axum::Json<PublicUser>> {
+    let Some(user) = user_svc.get_user(user_id).await.map_err(AppError::from)? else {
+        return Err(AppError::not_found("error-user-not-found").into());
+    };
+    Ok(axum::Json(user.into()))
+}
+
+pub fn router() -> Router<ApiRouteState> {
+    Router::new()
+        .route("/", get(list_users).post(create_user))
+        .route("/{id}", get(get_user))
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::header,
+    response::{self, IntoResponse, Redirect},
+    routing::{get, post},
+};
+use macros::ert;
+use tera::Tera;
+use tokio::sync::RwLock;
+use tracing::error;
+
+use crate::i18n::Message;
+use crate::routes::blobs;
+use crate::services::attachments::MAX_ATTACHMENT_BYTES;
+use crate::services::avatars::{self, AVATAR_SIZES};
+use crate::services::blobs::BlobStore;
+use crate::services::users::UserService;
+use crate::views::users::Avatar;
+use crate::{AppError, templating};
+
+// the avatar of a user may change, so clients check back now and then
+const AVATAR_CACHE_CONTROL: &str = "public, max-age=60";
+
+/// Redirects to the stored avatar of the user in one of `AVATAR_SIZES`, or
+/// answers with their identicon when they have none.
+async fn get_avatar<UserSvc: UserService, Blobs: BlobStore>(
+    State((usersvc, _, _)): State<AvatarRoutesState<UserSvc, Blobs>>,
+    Path((user_id, size)): Path<(i32, u32)>,
+) -> response::Result<response::Response> {
+    if !AVATAR_SIZES.contains(&size) {
+        return Err(AppError::not_found("error-avatar-size-not-found").into());
+    }
+    let Some(user) = usersvc.get_user(user_id).await.map_err(AppError::from)? else {
+        return Err(AppError::not_found("error-user-not-found").into());
+    };
+
+    let cache = [(header::CACHE_CONTROL, AVATAR_CACHE_CONTROL)];
+    if let Some(hash) = user.avatar_hash {
+        let to = format!("/blobs/{}", avatars::avatar_key(&hash, size));
+        return Ok((cache, Redirect::temporary(&to)).into_response());
+    }
+
+    let png = tokio::task::spawn_blocking(move || avatars::identicon(user_id, size))
+        .await
+        .map_err(AppError::from)?
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok((cache, [(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
+/// Takes a new avatar from the `avatar` file of a `multipart/form-data` body.
+async fn upload_avatar<UserSvc: UserService, Blobs: BlobStore>(
+    State((usersvc, blob_store, tera)): State<AvatarRoutesState<UserSvc, Blobs>>,
+    Path(user_id): Path<i32>,
+    mut multipart: Multipart,
+) -> response::Result<response::Html<String>> {
+    let bad_request = AppError::Validation;
+
+    let mut upload = None;
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| bad_request(Message::text(e.body_text())))?
+    {
+        if field.name() == Some("avatar") {
+            upload = Some(blobs::read_upload(&mut field).await?);
+        }
+    }
+    let Some(upload) = upload.filter(|u| !u.is_empty()) else {
+        return Err(bad_request("error-avatar-missing".into()).into());
+    };
+
+    let avatar = tokio::task::spawn_blocking(move || avatars::process_avatar(&upload))
+        .await
+        .map_err(AppError::from)?
+        .map_err(|e| bad_request(e.message()))?;
+    let hash = avatars::store_avatar(&blob_store, avatar)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    if !usersvc
+        .set_avatar(user_id, &hash)
+        .await
+        .map_err(AppError::from)?
+    {
+        return Err(AppError::not_found("error-user-not-found").into());
+    }
+
+    let view = Avatar {
+        user_id,
+        avatar_hash: &hash,
+    };
+    Ok(response::Html(
+        templating::render(&*tera.read().await, &view).map_err(AppError::from)?,
+    ))
+}
+
+type AvatarRoutesState<T, B> = (T, B, Arc<RwLock<Tera>>);
+
+/// Mounted next to the routes in `routes::users`.
+pub fn router<UserSvc: UserService, Blobs: BlobStore>() -> Router<AvatarRoutesState<UserSvc, Blobs>>
+{
+    Router::new()
+        .route("/{id}/avatar/{size}", get(get_avatar::<UserSvc, Blobs>))
+        .route(
+            "/{id}/avatar",
+            post(upload_avatar::<UserSvc, Blobs>)
+                .layer(DefaultBodyLimit::max(MAX_ATTACHMENT_BYTES + 64 * 1024)),
+        )
+}
@@ -0,0 +1,58 @@
+use axum::{
+    Router,
+    extract::{Path, State, multipart::Field},
+    http::header,
+    response::{self, IntoResponse},
+    routing::get,
+};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::AppError;
+use crate::i18n::Message;
+use crate::services::attachments::{self, ImageRejected};
+use crate::services::blobs::BlobStore;
+
+/// Serves a stored blob. Keys are never reused, so clients may cache it
+/// forever.
+async fn get_blob<Blobs: BlobStore>(
+    State(blobs): State<Blobs>,
+    Path(key): Path<String>,
+) -> response::Result<response::Response> {
+    let Some(content) = blobs.get(&key).await.map_err(AppError::from)? else {
+        return Err(AppError::not_found("error-blob-not-found").into());
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachments::content_type(&key)),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+/// Reads an uploaded file, giving up with a 400 as soon as it grows past
+/// `MAX_ATTACHMENT_BYTES`.
+pub async fn read_upload(field: &mut Field<'_>) -> response::Result<Bytes> {
+    let bad_request = AppError::Validation;
+
+    let mut upload = BytesMut::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| bad_request(Message::text(e.body_text())))?
+    {
+        if upload.len() + chunk.len() > attachments::MAX_ATTACHMENT_BYTES {
+            return Err(bad_request(ImageRejected::TooLarge.message()).into());
+        }
+        upload.extend_from_slice(&chunk);
+    }
+    Ok(upload.freeze())
+}
+
+pub fn router<Blobs: BlobStore>() -> Router<Blobs> {
+    Router::new().route("/{*key}", get(get_blob::<Blobs>))
+}
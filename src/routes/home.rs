@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use axum::{Router, extract::State, response, response::Html, routing::get};
+use macros::ert;
+use tera::Tera;
+use tokio::sync::RwLock;
+use tracing::error;
+
+use crate::views::Index;
+use crate::{AppError, templating};
+
+/// The start page, `index.html`. Everything else is swapped into it.
+#[tracing::instrument(skip_all)]
+async fn index(State(tera): State<Arc<RwLock<Tera>>>) -> response::Result<Html<String>> {
+    let body = templating::render(&*tera.read().await, &Index {})
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(Html(body))
+}
+
+pub fn router() -> Router<Arc<RwLock<Tera>>> {
+    Router::new().route("/", get(index))
+}
@@ -0,0 +1,42 @@
+use axum::Router;
+use axum::extract::Form;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use serde::Deserialize;
+use unic_langid::LanguageIdentifier;
+
+use crate::middleware::htmx::HxRefresh;
+use crate::middleware::locale::COOKIE;
+use crate::{AppError, i18n};
+
+#[derive(Deserialize)]
+struct ChooseLocale {
+    lang: String,
+}
+
+/// Remembers the locale the user picked over what their browser asks for,
+/// and has htmx reload the page in it.
+async fn choose(jar: CookieJar, Form(f): Form<ChooseLocale>) -> axum::response::Result<Response> {
+    let locale = f
+        .lang
+        .parse::<LanguageIdentifier>()
+        .ok()
+        .filter(i18n::is_available)
+        .ok_or_else(|| AppError::validation("error-locale-unknown"))?;
+    let cookie = Cookie::build((COOKIE, locale.to_string()))
+        .path("/")
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .permanent();
+    Ok((jar.add(cookie), HxRefresh, StatusCode::NO_CONTENT).into_response())
+}
+
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new().route("/locale", post(choose))
+}
@@ -1,2 +1,10 @@
+pub mod api;
+pub mod avatars;
+pub mod blobs;
+pub mod home;
+pub mod locale;
+pub mod moderation;
+pub mod notifications;
 pub mod posts;
+pub mod revisions;
 pub mod users;
@@ -0,0 +1,264 @@
+use std::sync::Arc;
+
+use axum::{
+    Form, Router,
+    extract::{Path, State},
+    response::{self, Html},
+    routing::{get, post},
+};
+use chrono::{Duration, Utc};
+use macros::ert;
+use tera::Tera;
+use tokio::sync::RwLock;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::background::posts_broker::{self, PostsEvent};
+use crate::i18n::Message;
+use crate::middleware::session::Session;
+use crate::models::moderation::{ModeratePost, ReportPost, SuspendUser};
+use crate::services::blobs::BlobStore;
+use crate::services::moderation::ModerationService;
+use crate::services::notifications::NotificationServiceDb;
+use crate::services::{announce, attachments};
+use crate::views::moderation::Queue;
+use crate::{AppError, templating};
+
+const QUEUE_LIMIT: i64 = 50;
+const ACTIONS_LIMIT: i64 = 20;
+// a year, longer than that is a ban in all but name
+const MAX_SUSPENSION_HOURS: u32 = 24 * 365;
+
+/// Rejects users who are suspended. Every handler letting users post goes
+/// through here first.
+pub async fn ensure_can_post<ModSvc: ModerationService>(
+    mod_svc: &ModSvc,
+    user_id: i32,
+) -> response::Result<()> {
+    match mod_svc
+        .suspended_until(user_id)
+        .await
+        .map_err(AppError::from)?
+    {
+        Some(until) => {
+            let until = until.format("%Y-%m-%d %H:%M UTC").to_string();
+            Err(AppError::forbidden(Message::new("error-suspended").arg("until", until)).into())
+        }
+        None => Ok(()),
+    }
+}
+
+async fn ensure_moderator<ModSvc: ModerationService>(
+    mod_svc: &ModSvc,
+    user_id: i32,
+) -> response::Result<()> {
+    if mod_svc
+        .is_moderator(user_id)
+        .await
+        .map_err(AppError::from)?
+    {
+        Ok(())
+    } else {
+        Err(AppError::forbidden("error-moderators-only").into())
+    }
+}
+
+/// Anyone can report a post they can see.
+#[tracing::instrument(skip_all)]
+async fn report_post<ModSvc: ModerationService, Blobs: BlobStore>(
+    State((_, mod_svc, _, _, _)): State<ModerationRoutesState<ModSvc, Blobs>>,
+    Form(f): Form<ReportPost>,
+) -> response::Result<Html<String>> {
+    if f.reason.trim().is_empty() {
+        return Err(AppError::validation("error-reason-required").into());
+    }
+    if f.reason.chars().count() > 500 {
+        return Err(AppError::validation("error-reason-too-long").into());
+    }
+    if !mod_svc
+        .report(&f)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    {
+        return Err(AppError::not_found("error-post-not-found").into());
+    }
+    Ok(Html(Message::new("report-received").localize()))
+}
+
+/// The reported posts and the latest moderation actions.
+#[tracing::instrument(skip_all)]
+async fn queue<ModSvc: ModerationService, Blobs: BlobStore>(
+    State((tera, mod_svc, _, _, _)): State<ModerationRoutesState<ModSvc, Blobs>>,
+    session: Session,
+) -> response::Result<Html<String>> {
+    ensure_moderator(&mod_svc, session.user_id).await?;
+    render_queue(&tera, &mod_svc).await
+}
+
+/// Takes a post out of every list and live feed.
+#[tracing::instrument(skip_all)]
+async fn hide_post<ModSvc: ModerationService, Blobs: BlobStore>(
+    State((tera, mod_svc, _, rmq_conn_pool, _)): State<ModerationRoutesState<ModSvc, Blobs>>,
+    Path(post_id): Path<Uuid>,
+    session: Session,
+    Form(f): Form<ModeratePost>,
+) -> response::Result<Html<String>> {
+    ensure_moderator(&mod_svc, session.user_id).await?;
+    if mod_svc
+        .hide_post(session.user_id, post_id, f.reason.as_deref())
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+        .is_none()
+    {
+        return Err(AppError::not_found("error-post-not-found").into());
+    }
+
+    posts_broker::publish(&rmq_conn_pool, &PostsEvent::Retracted { post_id })
+        .await
+        .map_err(AppError::from)?;
+    render_queue(&tera, &mod_svc).await
+}
+
+/// Deletes a post for good, its replies and images with it.
+#[tracing::instrument(skip_all)]
+async fn delete_post<ModSvc: ModerationService, Blobs: BlobStore>(
+    State((tera, mod_svc, blobs, rmq_conn_pool, _)): State<ModerationRoutesState<ModSvc, Blobs>>,
+    Path(post_id): Path<Uuid>,
+    session: Session,
+    Form(f): Form<ModeratePost>,
+) -> response::Result<Html<String>> {
+    ensure_moderator(&mod_svc, session.user_id).await?;
+    let Some((_, deleted_attachments)) = mod_svc
+        .delete_post(session.user_id, post_id, f.reason.as_deref())
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    else {
+        return Err(AppError::not_found("error-post-not-found").into());
+    };
+    attachments::discard_attachments(&blobs, &deleted_attachments).await;
+
+    posts_broker::publish(&rmq_conn_pool, &PostsEvent::Retracted { post_id })
+        .await
+        .map_err(AppError::from)?;
+    render_queue(&tera, &mod_svc).await
+}
+
+/// Publishes a post the content filters held back.
+#[tracing::instrument(skip_all)]
+async fn approve_post<ModSvc: ModerationService, Blobs: BlobStore>(
+    State((tera, mod_svc, _, rmq_conn_pool, notify_svc)): State<
+        ModerationRoutesState<ModSvc, Blobs>,
+    >,
+    Path(post_id): Path<Uuid>,
+    session: Session,
+    Form(f): Form<ModeratePost>,
+) -> response::Result<Html<String>> {
+    ensure_moderator(&mod_svc, session.user_id).await?;
+    let Some((post, attachments)) = mod_svc
+        .approve_post(session.user_id, post_id, f.reason.as_deref())
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    else {
+        return Err(AppError::not_found("error-held-post-not-found").into());
+    };
+
+    announce::announce_post(&rmq_conn_pool, &notify_svc, &post, attachments)
+        .await
+        .map_err(AppError::from)?;
+    render_queue(&tera, &mod_svc).await
+}
+
+/// Closes the reports of a post that is fine as it is.
+#[tracing::instrument(skip_all)]
+async fn dismiss_reports<ModSvc: ModerationService, Blobs: BlobStore>(
+    State((tera, mod_svc, _, _, _)): State<ModerationRoutesState<ModSvc, Blobs>>,
+    Path(post_id): Path<Uuid>,
+    session: Session,
+    Form(f): Form<ModeratePost>,
+) -> response::Result<Html<String>> {
+    ensure_moderator(&mod_svc, session.user_id).await?;
+    mod_svc
+        .dismiss_reports(session.user_id, post_id, f.reason.as_deref())
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    render_queue(&tera, &mod_svc).await
+}
+
+/// Keeps a user from posting for `hours`.
+#[tracing::instrument(skip_all)]
+async fn suspend_user<ModSvc: ModerationService, Blobs: BlobStore>(
+    State((tera, mod_svc, _, _, _)): State<ModerationRoutesState<ModSvc, Blobs>>,
+    Path(user_id): Path<i32>,
+    session: Session,
+    Form(f): Form<SuspendUser>,
+) -> response::Result<Html<String>> {
+    ensure_moderator(&mod_svc, session.user_id).await?;
+    if !(1..=MAX_SUSPENSION_HOURS).contains(&f.hours) {
+        let msg = Message::new("error-suspension-hours").arg("max", MAX_SUSPENSION_HOURS);
+        return Err(AppError::validation(msg).into());
+    }
+
+    let until = Utc::now() + Duration::hours(f.hours.into());
+    if !mod_svc
+        .suspend_user(session.user_id, user_id, until, f.reason.as_deref())
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    {
+        return Err(AppError::not_found("error-user-not-found").into());
+    }
+    render_queue(&tera, &mod_svc).await
+}
+
+async fn render_queue<ModSvc: ModerationService>(
+    tera: &RwLock<Tera>,
+    mod_svc: &ModSvc,
+) -> response::Result<Html<String>> {
+    let reported = mod_svc.queue(QUEUE_LIMIT).await.map_err(AppError::from)?;
+    let held = mod_svc
+        .held_posts(QUEUE_LIMIT)
+        .await
+        .map_err(AppError::from)?;
+    let actions = mod_svc
+        .recent_actions(ACTIONS_LIMIT)
+        .await
+        .map_err(AppError::from)?;
+
+    let view = Queue {
+        reported: &reported,
+        held: &held,
+        actions: &actions,
+    };
+    let body = templating::render(&*tera.read().await, &view)
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(Html(body))
+}
+
+type ModerationRoutesState<M, B> = (
+    Arc<RwLock<Tera>>,
+    M,
+    B,
+    deadpool_lapin::Pool,
+    NotificationServiceDb,
+);
+
+pub fn router<ModSvc: ModerationService, Blobs: BlobStore>()
+-> Router<ModerationRoutesState<ModSvc, Blobs>> {
+    Router::new()
+        .route("/", get(queue::<ModSvc, Blobs>))
+        .route("/reports", post(report_post::<ModSvc, Blobs>))
+        .route("/posts/{id}/hide", post(hide_post::<ModSvc, Blobs>))
+        .route("/posts/{id}/delete", post(delete_post::<ModSvc, Blobs>))
+        .route("/posts/{id}/approve", post(approve_post::<ModSvc, Blobs>))
+        .route(
+            "/posts/{id}/dismiss",
+            post(dismiss_reports::<ModSvc, Blobs>),
+        )
+        .route("/users/{id}/suspend", post(suspend_user::<ModSvc, Blobs>))
+}
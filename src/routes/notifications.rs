@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use axum::{
+    Form, Router,
+    extract::{Path, Query, State},
+    response,
+    routing::{get, post},
+};
+use tera::Tera;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::models::notification::ListNotifications;
+use crate::services::notifications::NotificationService;
+use crate::views::notifications::{Count, List};
+use crate::{AppError, models, templating};
+
+const LIST_LIMIT: i64 = 50;
+
+async fn list<NotifySvc: NotificationService>(
+    State((tera, notifysvc, _)): State<NotificationRoutesState<NotifySvc>>,
+    Query(q): Query<ListNotifications>,
+) -> response::Result<response::Html<String>> {
+    render_list(&tera, &notifysvc, q.user_id).await
+}
+
+async fn count<NotifySvc: NotificationService>(
+    State((tera, notifysvc, _)): State<NotificationRoutesState<NotifySvc>>,
+    Query(q): Query<ListNotifications>,
+) -> response::Result<response::Html<String>> {
+    let unread = notifysvc
+        .unread_count(q.user_id)
+        .await
+        .map_err(AppError::from)?;
+
+    Ok(response::Html(
+        templating::render(&*tera.read().await, &Count { unread }).map_err(AppError::from)?,
+    ))
+}
+
+async fn mark_read<NotifySvc: NotificationService>(
+    State((tera, notifysvc, _)): State<NotificationRoutesState<NotifySvc>>,
+    Path(id): Path<Uuid>,
+    Form(f): Form<models::ActingUser>,
+) -> response::Result<response::Html<String>> {
+    notifysvc
+        .mark_read(f.user_id, Some(id))
+        .await
+        .map_err(AppError::from)?;
+    render_list(&tera, &notifysvc, f.user_id).await
+}
+
+async fn mark_all_read<NotifySvc: NotificationService>(
+    State((tera, notifysvc, _)): State<NotificationRoutesState<NotifySvc>>,
+    Form(f): Form<models::ActingUser>,
+) -> response::Result<response::Html<String>> {
+    notifysvc
+        .mark_read(f.user_id, None)
+        .await
+        .map_err(AppError::from)?;
+    render_list(&tera, &notifysvc, f.user_id).await
+}
+
+async fn render_list<NotifySvc: NotificationService>(
+    tera: &RwLock<Tera>,
+    notifysvc: &NotifySvc,
+    user_id: i32,
+) -> response::Result<response::Html<String>> {
+    let notifications = notifysvc
+        .list(user_id, LIST_LIMIT)
+        .await
+        .map_err(AppError::from)?;
+    let unread = notifysvc
+        .unread_count(user_id)
+        .await
+        .map_err(AppError::from)?;
+
+    let view = List {
+        user_id,
+        unread,
+        notifications: &notifications,
+    };
+    Ok(response::Html(
+        templating::render(&*tera.read().await, &view).map_err(AppError::from)?,
+    ))
+}
+
+type NotificationRoutesState<T> = (Arc<RwLock<Tera>>, T, deadpool_lapin::Pool);
+
+pub fn router<NotifySvc: NotificationService>() -> Router<NotificationRoutesState<NotifySvc>> {
+    Router::new()
+        .route("/", get(list::<NotifySvc>))
+        .route("/count", get(count::<NotifySvc>))
+        .route("/read", post(mark_all_read::<NotifySvc>))
+        .route("/{id}/read", post(mark_read::<NotifySvc>))
+}
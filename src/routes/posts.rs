@@ -2,146 +2,701 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use axum::extract::ws::WebSocketUpgrade;
-use axum::extract::{Request, State};
-use axum::response::{Html, IntoResponse};
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, Request, State};
+use axum::http::{HeaderMap, header};
+use axum::response::{Html, IntoResponse, Response};
 use axum::routing::post;
 use axum::{Form, RequestExt, Router};
 use axum::{extract::ws::Message, routing::get};
 use bytes::Bytes;
+use chrono::Utc;
 use futures_util::StreamExt;
-use lapin::BasicProperties;
-use lapin::options::BasicPublishOptions;
 use macros::ert;
+use serde::Deserialize;
 use tera::Tera;
 use tokio::sync::RwLock;
 use tracing::{Span, error, info, warn};
+use uuid::Uuid;
 
-use crate::background::posts_broker::PostsSubscriptionManager;
+use crate::background::posts_broker::{self, Feed, PostsEvent, PostsSubscriptionManager};
 use crate::error::AppError;
-use crate::models::post::Post;
+use crate::i18n;
+use crate::middleware::htmx::{HxPage, HxPushUrl, HxRequest};
+use crate::middleware::session::Session;
+use crate::models::ActingUser;
+use crate::models::attachment::Attachment;
+use crate::models::empty_string_as_none;
+use crate::models::notification::{NewNotification, NotificationKind};
+use crate::models::post::{
+    CreatePost, CreateReply, HomeFeed, NewPost, Post, PostStatus, PostThreadNode, SearchPosts,
+};
+use crate::models::reaction::{Reaction, ReactionCounts};
+use crate::routes::{blobs, moderation};
 use crate::services::Pool;
+use crate::services::announce::{self, announce_post, mention_notifications};
+use crate::services::attachments;
+use crate::services::blobs::AppBlobStore;
+use crate::services::content_filter::{Candidate, ContentFilterChain, Verdict};
+use crate::services::follows::{FollowService, FollowServiceDb};
+use crate::services::moderation::ModerationServiceDb;
+use crate::services::notifications::NotificationServiceDb;
+use crate::services::posts::{PostService, PostServiceDb};
+use crate::templating;
+use crate::views::notifications::WsNotification;
+use crate::views::posts::{
+    DraftEdit, DraftSaved, Drafts, FeedPage, PostCreated, Reactions, ReplyCreated, Search, Thread,
+    WsFeedPost, WsPost, WsReactions, WsReply, WsRetracted,
+};
 
 type PostsRouteState = (
     Arc<RwLock<Tera>>,
     Arc<PostsSubscriptionManager>,
     deadpool_lapin::Pool,
     Pool,
+    PostServiceDb,
+    FollowServiceDb,
+    NotificationServiceDb,
+    AppBlobStore,
+    ModerationServiceDb,
+    ContentFilterChain,
 );
 
+const SEARCH_LIMIT: i64 = 50;
+const FEED_PAGE_SIZE: i64 = 20;
+
+// the query string of `GET /posts/ws`
+#[derive(Deserialize, Debug)]
+struct WsParams {
+    /// only deliver replies to this thread instead of the global feed
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    thread: Option<Uuid>,
+    /// only deliver posts by the users this user follows
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    following: Option<i32>,
+    /// only deliver the notifications of the signed in user, no posts
+    #[serde(default)]
+    personal: bool,
+}
+
+// a new post looks different in each kind of feed
+type RenderCreated = fn(&Tera, &Post, &[Attachment]) -> tera::Result<String>;
+
 async fn ws(
-    State((tera, sub_mgr, _, _)): State<PostsRouteState>,
+    State((tera, sub_mgr, _, _, _, follow_svc, _, _, _, _)): State<PostsRouteState>,
+    Query(params): Query<WsParams>,
+    session: Option<Session>,
     wsu: WebSocketUpgrade,
 ) -> axum::response::Result<impl IntoResponse> {
+    // notifications only ever go to the user the browser is signed in as
+    if params.personal && session.is_none() {
+        return Err(AppError::unauthorized("error-signed-out").into());
+    }
     info!("ahhhh");
     let s = Span::current();
     info!("span id: {:?}", s.id());
+    let render_post: RenderCreated = |tera, post, attachments| {
+        let reactions = &ReactionCounts::new();
+        templating::render(
+            tera,
+            &WsPost {
+                post,
+                reactions,
+                attachments,
+            },
+        )
+    };
+    let (feed, render_created): (_, RenderCreated) = match (params.thread, params.following) {
+        _ if params.personal => (Feed::Personal, render_post),
+        (Some(root), _) => (Feed::Thread(root), |tera, post, attachments| {
+            let reactions = &ReactionCounts::new();
+            templating::render(
+                tera,
+                &WsReply {
+                    post,
+                    reactions,
+                    attachments,
+                },
+            )
+        }),
+        (None, Some(user_id)) => {
+            let followees = follow_svc
+                .following_ids(user_id)
+                .await
+                .map_err(AppError::from)?;
+            (
+                Feed::Following { user_id, followees },
+                |tera, post, attachments| {
+                    let reactions = &ReactionCounts::new();
+                    templating::render(
+                        tera,
+                        &WsFeedPost {
+                            post,
+                            reactions,
+                            attachments,
+                        },
+                    )
+                },
+            )
+        }
+        (None, None) => (Feed::Global, render_post),
+    };
+    // the socket is served on a task of its own, outside the request's locale
+    let locale = i18n::current();
     let res = wsu
         .on_failed_upgrade(|e| {
             error!(target: "ahh", "ws upgrade failed: {:?}", e);
         })
-        .on_upgrade(|mut ws| async move {
-            info!("new ws conn");
-
-            let subscription = sub_mgr.subscribe();
-            let id = subscription.id;
-            let mut stream = tokio_stream::wrappers::ReceiverStream::from(subscription.rx);
-
-            while let Some(x) = stream.next().await {
-                info!("new post");
-                let mut ctx = tera::Context::new();
-                ctx.insert("post", x.as_ref());
-                let html = tera
-                    .read()
-                    .await
-                    .render("posts/ws_post.html", &ctx)
-                    .inspect_err(ert!())
-                    .unwrap_or_default();
-                if let Err(e) = ws.send(Message::Ping(Bytes::from_static(b"foo"))).await {
-                    warn!(%e, "ws ping failed");
-                    continue;
-                }
-                match ws.send(Message::Text(html.into())).await {
-                    Ok(_) => (),
-                    Err(e) => {
-                        warn!(%e, "ws died");
-                        let _ = sub_mgr
-                            .unsubscribe(&id)
-                            .ok_or_else(|| anyhow!("already unsubscribed: {}", &id))
-                            .inspect_err(ert!());
-                        return;
+        .on_upgrade(move |mut ws| {
+            i18n::scope(locale, async move {
+                info!("new ws conn");
+
+                let subscription = sub_mgr.subscribe(feed, session.map(|s| s.user_id));
+                let id = subscription.id;
+                let mut stream = tokio_stream::wrappers::ReceiverStream::from(subscription.rx);
+
+                while let Some(x) = stream.next().await {
+                    info!("new posts event");
+                    let tera = tera.read().await;
+                    let html = match x.as_ref() {
+                        PostsEvent::Created { post, attachments } => {
+                            render_created(&tera, post, attachments)
+                        }
+                        PostsEvent::Reactions { post_id, reactions } => {
+                            let view = WsReactions {
+                                post_id: *post_id,
+                                reactions,
+                            };
+                            templating::render(&tera, &view)
+                        }
+                        PostsEvent::Retracted { post_id } => {
+                            templating::render(&tera, &WsRetracted { post_id: *post_id })
+                        }
+                        PostsEvent::Notification {
+                            notification,
+                            unread,
+                        } => {
+                            let view = WsNotification {
+                                notification,
+                                unread: *unread,
+                            };
+                            templating::render(&tera, &view)
+                        }
+                    };
+                    drop(tera);
+                    let html = html.inspect_err(ert!()).unwrap_or_default();
+                    if let Err(e) = ws.send(Message::Ping(Bytes::from_static(b"foo"))).await {
+                        warn!(%e, "ws ping failed");
+                        continue;
                     }
-                };
-            }
+                    match ws.send(Message::Text(html.into())).await {
+                        Ok(_) => (),
+                        Err(e) => {
+                            warn!(%e, "ws died");
+                            let _ = sub_mgr
+                                .unsubscribe(&id)
+                                .ok_or_else(|| anyhow!("already unsubscribed: {}", &id))
+                                .inspect_err(ert!());
+                            return;
+                        }
+                    };
+                }
 
-            info!("done sending posts");
+                info!("done sending posts");
+            })
         });
     Ok(res)
 }
 
 #[tracing::instrument(skip_all)]
 async fn create_post(
-    State((tera, _, rmq_conn_pool, db_pool)): State<PostsRouteState>,
+    State((tera, _, rmq_conn_pool, _, post_svc, _, notify_svc, blobs, mod_svc, filters)): State<
+        PostsRouteState,
+    >,
     req: Request,
 ) -> axum::response::Result<Html<Bytes>> {
-    use crate::models::post::CreatePost;
-    use crate::schema::posts::dsl::*;
-    use diesel_async::RunQueryDsl;
+    let (f, uploads) = if is_multipart(req.headers()) {
+        let multipart: Multipart = req.extract().await.map_err(AppError::from)?;
+        read_create_post(multipart).await?
+    } else {
+        let Form(f): Form<CreatePost> = req.extract().await.map_err(AppError::from)?;
+        (f, vec![])
+    };
+    moderation::ensure_can_post(&mod_svc, f.user_id).await?;
+    let (status, publish_at) = f.status(Utc::now()).map_err(AppError::Validation)?;
+    let held = if status == PostStatus::Draft {
+        None
+    } else {
+        let candidate = Candidate {
+            user_id: f.user_id,
+            content: &f.post_content,
+            post_id: None,
+        };
+        screen(&filters, &candidate).await?
+    };
 
-    let Form(f): Form<CreatePost> = req.extract().await.map_err(AppError::from)?;
-    let mut conn = db_pool.get().await.map_err(AppError::from)?;
+    let images = tokio::task::spawn_blocking(move || {
+        uploads
+            .iter()
+            .map(|u| attachments::process_image(u))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(AppError::from)?
+    .map_err(|e| AppError::validation(e.message()))?;
+    let stored = attachments::store_images(&blobs, images)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
 
-    let post = diesel::insert_into(posts)
-        .values(f)
-        .get_result::<Post>(&mut conn)
+    let mut new = NewPost::new(&f, status, publish_at);
+    if let Some(reason) = held {
+        new = new.hold(reason);
+    }
+    let (post, attachments) = match post_svc.create_post(&new, &stored).await {
+        Ok(created) => created,
+        Err(e) => {
+            attachments::discard_images(&blobs, &stored).await;
+            return Err(AppError::from(e).into());
+        }
+    };
+
+    if post.status != PostStatus::Published {
+        return Ok(Html(Bytes::from(render_draft_saved(&tera, &post).await?)));
+    }
+    announce_post(&rmq_conn_pool, &notify_svc, &post, attachments.clone())
         .await
         .map_err(AppError::from)?;
 
-    let channel = rmq_conn_pool
-        .get()
+    let view = PostCreated {
+        post: &post,
+        reactions: &ReactionCounts::new(),
+        attachments: &attachments,
+    };
+    let body = templating::render(&*tera.read().await, &view)
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(Html(Bytes::from(body)))
+}
+
+fn is_multipart(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"))
+}
+
+/// Reads a `multipart/form-data` post: the fields of `CreatePost`, parsed
+/// as if they came in a urlencoded form, and up to `MAX_ATTACHMENTS` files
+/// in `attachments`.
+async fn read_create_post(
+    mut multipart: Multipart,
+) -> axum::response::Result<(CreatePost, Vec<Bytes>)> {
+    let bad_request = AppError::Validation;
+
+    let (mut fields, mut uploads) = (Vec::new(), Vec::new());
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| bad_request(i18n::Message::text(e.body_text())))?
+    {
+        match field.name().map(str::to_owned) {
+            // browsers send an empty, nameless file when none was picked
+            Some(name) if name == "attachments" => {
+                if field.file_name().is_none_or(str::is_empty) {
+                    continue;
+                }
+                if uploads.len() == attachments::MAX_ATTACHMENTS {
+                    let msg = i18n::Message::new("error-too-many-attachments")
+                        .arg("max", attachments::MAX_ATTACHMENTS);
+                    return Err(bad_request(msg).into());
+                }
+                uploads.push(blobs::read_upload(&mut field).await?);
+            }
+            Some(name) => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| bad_request(i18n::Message::text(e.body_text())))?;
+                fields.push((name, text));
+            }
+            None => {}
+        }
+    }
+
+    let form = serde_urlencoded::to_string(&fields).map_err(AppError::from)?;
+    let f = serde_urlencoded::from_str(&form).map_err(|e| {
+        bad_request(i18n::Message::text(format!(
+            "Failed to deserialize form body: {e}"
+        )))
+    })?;
+    Ok((f, uploads))
+}
+
+/// Runs a post through the content filters. A rejected post is answered
+/// with the reason for the author to fix it, a held one gets the reason to
+/// store with it.
+pub async fn screen(
+    filters: &ContentFilterChain,
+    candidate: &Candidate<'_>,
+) -> axum::response::Result<Option<String>> {
+    match filters
+        .check(candidate)
         .await
-        .map_err(AppError::from)
-        .inspect_err(ert!())?
-        .create_channel()
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    {
+        Verdict::Allow => Ok(None),
+        Verdict::Hold(reason) => Ok(Some(reason)),
+        Verdict::Reject(reason) => {
+            let msg = i18n::Message::new("error-post-rejected").arg("reason", reason.localize());
+            Err(AppError::validation(msg).into())
+        }
+    }
+}
+
+pub async fn render_draft_saved(
+    tera: &RwLock<Tera>,
+    post: &Post,
+) -> axum::response::Result<String> {
+    let body = templating::render(&*tera.read().await, &DraftSaved { post })
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(body)
+}
+
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("application/json"))
+}
+
+/// The home feed of `user_id`: posts by everyone they follow, a page at a
+/// time. `posts/feed.html` appends the next page when scrolled into view.
+#[tracing::instrument(skip_all)]
+async fn feed(
+    State((tera, _, _, _, post_svc, _, _, _, _, _)): State<PostsRouteState>,
+    headers: HeaderMap,
+    hx: HxRequest,
+    Query(params): Query<HomeFeed>,
+) -> axum::response::Result<Response> {
+    let page = post_svc
+        .home_feed(params.user_id, params.cursor, FEED_PAGE_SIZE)
         .await
-        .map_err(AppError::from)
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+
+    if wants_json(&headers) {
+        return Ok(axum::Json(page).into_response());
+    }
+
+    let view = FeedPage {
+        user_id: params.user_id,
+        first_page: params.cursor.is_none(),
+        page: &page,
+    };
+    let body = hx
+        .render(&*tera.read().await, "title-home-feed", &view)
         .inspect_err(ert!())?;
-    channel
-        .basic_publish(
-            "",
-            "posts",
-            BasicPublishOptions {
-                mandatory: true,
-                ..Default::default()
-            },
-            serde_json::to_vec(&post)
-                .inspect_err(ert!())
-                .map_err(AppError::from)?
-                .as_slice(),
-            BasicProperties::default().with_content_type("application/json".into()),
+    Ok(body.into_response())
+}
+
+/// Renders the `posts/search.html` fragment for htmx live search, or the raw
+/// hits for clients sending `Accept: application/json`.
+#[tracing::instrument(skip_all)]
+async fn search(
+    State((tera, _, _, _, post_svc, _, _, _, _, _)): State<PostsRouteState>,
+    headers: HeaderMap,
+    Query(params): Query<SearchPosts>,
+) -> axum::response::Result<Response> {
+    let hits = post_svc
+        .search(&params, SEARCH_LIMIT)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+
+    if wants_json(&headers) {
+        return Ok(axum::Json(hits).into_response());
+    }
+
+    let view = Search {
+        q: &params.q,
+        hits: &hits,
+    };
+    let body = templating::render(&*tera.read().await, &view)
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(Html(body).into_response())
+}
+
+#[tracing::instrument(skip_all)]
+async fn create_reply(
+    State((tera, _, rmq_conn_pool, _, post_svc, _, notify_svc, _, mod_svc, filters)): State<
+        PostsRouteState,
+    >,
+    Path(parent): Path<Uuid>,
+    Form(f): Form<CreateReply>,
+) -> axum::response::Result<Html<String>> {
+    moderation::ensure_can_post(&mod_svc, f.user_id).await?;
+    let candidate = Candidate {
+        user_id: f.user_id,
+        content: &f.post_content,
+        post_id: None,
+    };
+    let held = screen(&filters, &candidate).await?;
+    let Some(reply) = post_svc
+        .create_reply(parent, &f, held)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    else {
+        return Err(AppError::not_found("error-post-not-found").into());
+    };
+    if reply.status == PostStatus::Held {
+        return Ok(Html(render_draft_saved(&tera, &reply).await?));
+    }
+
+    posts_broker::publish(
+        &rmq_conn_pool,
+        &PostsEvent::Created {
+            post: reply.clone(),
+            attachments: vec![],
+        },
+    )
+    .await
+    .map_err(AppError::from)?;
+
+    let mut new = mention_notifications(&notify_svc, &reply).await;
+    if let Ok(Some(parent)) = post_svc.get_post(parent).await.inspect_err(ert!()) {
+        new.push(NewNotification {
+            user_id: parent.user_id,
+            kind: NotificationKind::Reply,
+            actor_id: reply.user_id,
+            post_id: Some(reply.id),
+        });
+    }
+    announce::deliver(&notify_svc, &rmq_conn_pool, new).await;
+
+    let body = templating::render(&*tera.read().await, &ReplyCreated { post: &reply })
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(Html(body))
+}
+
+/// Renders the whole thread a post belongs to, starting at its root. Opened
+/// in the thread pane of the start page, its URL goes into the history so it
+/// can be reloaded or shared as a page of its own.
+#[tracing::instrument(skip_all)]
+async fn thread(
+    State((tera, _, _, _, post_svc, _, _, _, _, _)): State<PostsRouteState>,
+    hx: HxRequest,
+    Path(post_id): Path<Uuid>,
+) -> axum::response::Result<(Option<HxPushUrl>, HxPage)> {
+    let thread = load_thread(&post_svc, post_id).await?;
+
+    let body = hx
+        .render(
+            &*tera.read().await,
+            "title-thread",
+            &Thread { thread: &thread },
         )
+        .inspect_err(ert!())?;
+    let push = hx
+        .targets("post-thread")
+        .then(|| HxPushUrl(format!("/posts/{post_id}/thread")));
+    Ok((push, body))
+}
+
+/// The whole thread `post_id` is part of, from its root down.
+pub async fn load_thread<PostSvc: PostService>(
+    post_svc: &PostSvc,
+    post_id: Uuid,
+) -> axum::response::Result<PostThreadNode> {
+    let not_found = || AppError::not_found("error-post-not-found");
+
+    let post = post_svc
+        .get_post(post_id)
+        .await
+        .map_err(AppError::from)?
+        .ok_or_else(not_found)?;
+    let root = match post.root_id {
+        Some(root_id) => post_svc
+            .get_post(root_id)
+            .await
+            .map_err(AppError::from)?
+            .ok_or_else(not_found)?,
+        None => post,
+    };
+    let replies = post_svc.get_thread(root.id).await.map_err(AppError::from)?;
+    let ids: Vec<Uuid> = std::iter::once(root.id)
+        .chain(replies.iter().map(|p| p.id))
+        .collect();
+    let reactions = post_svc
+        .reaction_counts(&ids)
+        .await
+        .map_err(AppError::from)?;
+    let attachments = post_svc.attachments(&ids).await.map_err(AppError::from)?;
+    Ok(PostThreadNode::build(root, replies, reactions, attachments))
+}
+
+/// Gives or takes back a reaction and answers with the post's updated
+/// reaction bar. Everyone else watching the post gets it over `/posts/ws`.
+#[tracing::instrument(skip_all)]
+async fn toggle_reaction(
+    State((tera, _, rmq_conn_pool, _, post_svc, _, notify_svc, _, _, _)): State<PostsRouteState>,
+    Path((post_id, reaction)): Path<(Uuid, Reaction)>,
+    Form(f): Form<ActingUser>,
+) -> axum::response::Result<Html<String>> {
+    let Some((given, reactions)) = post_svc
+        .toggle_reaction(post_id, f.user_id, reaction)
         .await
-        .map_err(AppError::from)
-        .inspect_err(ert!())?
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    else {
+        return Err(AppError::not_found("error-post-not-found").into());
+    };
+
+    let view = Reactions {
+        post_id,
+        reactions: &reactions,
+    };
+    let body = templating::render(&*tera.read().await, &view)
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+
+    posts_broker::publish(
+        &rmq_conn_pool,
+        &PostsEvent::Reactions { post_id, reactions },
+    )
+    .await
+    .map_err(AppError::from)?;
+
+    if given && let Ok(Some(post)) = post_svc.get_post(post_id).await.inspect_err(ert!()) {
+        let new = vec![NewNotification {
+            user_id: post.user_id,
+            kind: NotificationKind::Reaction,
+            actor_id: f.user_id,
+            post_id: Some(post_id),
+        }];
+        announce::deliver(&notify_svc, &rmq_conn_pool, new).await;
+    }
+
+    Ok(Html(body))
+}
+
+/// The drafts and scheduled posts of a user, newest change first.
+#[tracing::instrument(skip_all)]
+async fn drafts(
+    State((tera, _, _, _, post_svc, _, _, _, _, _)): State<PostsRouteState>,
+    hx: HxRequest,
+    Query(acting): Query<ActingUser>,
+) -> axum::response::Result<HxPage> {
+    let drafts = post_svc
+        .drafts(acting.user_id)
         .await
         .inspect_err(ert!())
-        .map_err(|e| Html(e.to_string()))?;
+        .map_err(AppError::from)?;
 
-    let teractx =
-        tera::Context::from_value(serde_json::json!({"post": post})).map_err(|e| AppError {
-            inner: anyhow!("could not template response: {}", e),
-        })?;
+    let view = Drafts {
+        user_id: acting.user_id,
+        drafts: &drafts,
+    };
+    Ok(hx
+        .render(&*tera.read().await, "title-drafts", &view)
+        .inspect_err(ert!())?)
+}
 
-    let body = tera
-        .read()
+/// The form to edit, schedule or publish a draft of the acting user.
+#[tracing::instrument(skip_all)]
+async fn edit_draft(
+    State((tera, _, _, _, post_svc, _, _, _, _, _)): State<PostsRouteState>,
+    Path(post_id): Path<Uuid>,
+    Query(acting): Query<ActingUser>,
+) -> axum::response::Result<Html<String>> {
+    let Some(draft) = post_svc
+        .get_draft(acting.user_id, post_id)
+        .await
+        .map_err(AppError::from)?
+    else {
+        return Err(AppError::not_found("error-draft-not-found").into());
+    };
+    let attachments = post_svc
+        .attachments(&[draft.id])
         .await
-        .render("posts/create_post.html", &teractx)
+        .map_err(AppError::from)?
+        .remove(&draft.id)
+        .unwrap_or_default();
+
+    let view = DraftEdit {
+        post: &draft,
+        attachments: &attachments,
+    };
+    let body = templating::render(&*tera.read().await, &view)
         .inspect_err(ert!())
         .map_err(AppError::from)?;
-    Ok(Html(Bytes::from(body)))
+    Ok(Html(body))
+}
+
+/// Saves a draft again, schedules it or publishes it right away. A draft
+/// published here goes out just like one posted through `create_post`.
+#[tracing::instrument(skip_all)]
+async fn update_draft(
+    State((tera, _, rmq_conn_pool, _, post_svc, _, notify_svc, _, mod_svc, filters)): State<
+        PostsRouteState,
+    >,
+    Path(post_id): Path<Uuid>,
+    Form(f): Form<CreatePost>,
+) -> axum::response::Result<Html<String>> {
+    moderation::ensure_can_post(&mod_svc, f.user_id).await?;
+    let (status, publish_at) = f.status(Utc::now()).map_err(AppError::Validation)?;
+    let mut new = NewPost::new(&f, status, publish_at);
+    if status != PostStatus::Draft {
+        let candidate = Candidate {
+            user_id: f.user_id,
+            content: &f.post_content,
+            post_id: Some(post_id),
+        };
+        if let Some(reason) = screen(&filters, &candidate).await? {
+            new = new.hold(reason);
+        }
+    }
+    let Some(post) = post_svc
+        .update_draft(post_id, &new)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    else {
+        return Err(AppError::not_found("error-draft-not-found").into());
+    };
+
+    if post.status == PostStatus::Published {
+        let attachments = post_svc
+            .attachments(&[post.id])
+            .await
+            .map_err(AppError::from)?
+            .remove(&post.id)
+            .unwrap_or_default();
+        announce_post(&rmq_conn_pool, &notify_svc, &post, attachments)
+            .await
+            .map_err(AppError::from)?;
+    }
+    Ok(Html(render_draft_saved(&tera, &post).await?))
 }
 
 pub fn router() -> Router<PostsRouteState> {
     Router::new()
         .route("/ws", get(ws))
-        .route("/", post(create_post))
+        .route("/feed", get(feed))
+        .route("/search", get(search))
+        .route("/drafts", get(drafts))
+        .route("/drafts/{id}", get(edit_draft).post(update_draft))
+        .route("/{id}/thread", get(thread))
+        .route("/{id}/replies", post(create_reply))
+        .route("/{id}/reactions/{reaction}", post(toggle_reaction))
+        .route(
+            "/",
+            post(create_post).layer(DefaultBodyLimit::max(
+                attachments::MAX_ATTACHMENTS * attachments::MAX_ATTACHMENT_BYTES + 64 * 1024,
+            )),
+        )
 }
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use axum::{
+    Form, Router,
+    extract::{Path, Query, State},
+    response::{self, Html},
+    routing::{get, post},
+};
+use macros::ert;
+use tera::Tera;
+use tokio::sync::RwLock;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::background::posts_broker::{self, PostsEvent};
+use crate::middleware::session::Session;
+use crate::models::ActingUser;
+use crate::models::post::{Post, PostStatus, parse_tags};
+use crate::models::revision::{EditPost, PostEdit, RevisionView};
+use crate::routes::{moderation, posts};
+use crate::services::content_filter::{Candidate, ContentFilterChain};
+use crate::services::moderation::ModerationService;
+use crate::services::posts::PostService;
+use crate::views::posts::{Edit, Revisions};
+use crate::{AppError, templating};
+
+/// The form to edit a published post, for its author only.
+#[tracing::instrument(skip_all)]
+async fn edit_form<PostSvc: PostService, ModSvc: ModerationService>(
+    State((tera, post_svc, _, _, _)): State<RevisionRoutesState<PostSvc, ModSvc>>,
+    Path(post_id): Path<Uuid>,
+    Query(acting): Query<ActingUser>,
+) -> response::Result<Html<String>> {
+    let Some(post) = post_svc.get_post(post_id).await.map_err(AppError::from)? else {
+        return Err(AppError::not_found("error-post-not-found").into());
+    };
+    if post.user_id != acting.user_id {
+        return Err(AppError::forbidden("error-edit-not-author").into());
+    }
+
+    let body = templating::render(&*tera.read().await, &Edit { post: &post })
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(Html(body))
+}
+
+/// Saves an edit by the author and answers with the post's history.
+#[tracing::instrument(skip_all)]
+async fn edit_post<PostSvc: PostService, ModSvc: ModerationService>(
+    State((tera, post_svc, mod_svc, filters, rmq_conn_pool)): State<
+        RevisionRoutesState<PostSvc, ModSvc>,
+    >,
+    Path(post_id): Path<Uuid>,
+    Form(f): Form<EditPost>,
+) -> response::Result<Html<String>> {
+    moderation::ensure_can_post(&mod_svc, f.user_id).await?;
+    let tags = f.tags.as_deref().map(parse_tags).unwrap_or_default();
+    let mut edit = PostEdit::new(f.user_id, &f.post_content, tags);
+    let candidate = Candidate {
+        user_id: f.user_id,
+        content: &f.post_content,
+        post_id: Some(post_id),
+    };
+    if let Some(reason) = posts::screen(&filters, &candidate).await? {
+        edit = edit.hold(reason);
+    }
+    let Some(post) = post_svc
+        .edit_post(post_id, &edit)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    else {
+        // a post by someone else looks like no post at all
+        return Err(AppError::not_found("error-post-not-found").into());
+    };
+    if post.status == PostStatus::Held {
+        // the published version goes away until a moderator has had a look
+        posts_broker::publish(&rmq_conn_pool, &PostsEvent::Retracted { post_id })
+            .await
+            .map_err(AppError::from)?;
+        return Ok(Html(posts::render_draft_saved(&tera, &post).await?));
+    }
+    render_revisions(&tera, &post_svc, post).await
+}
+
+#[tracing::instrument(skip_all)]
+async fn revisions<PostSvc: PostService, ModSvc: ModerationService>(
+    State((tera, post_svc, _, _, _)): State<RevisionRoutesState<PostSvc, ModSvc>>,
+    Path(post_id): Path<Uuid>,
+) -> response::Result<Html<String>> {
+    let Some(post) = post_svc.get_post(post_id).await.map_err(AppError::from)? else {
+        return Err(AppError::not_found("error-post-not-found").into());
+    };
+    render_revisions(&tera, &post_svc, post).await
+}
+
+/// Puts an earlier version of a post back. Moderators only.
+#[tracing::instrument(skip_all)]
+async fn restore_revision<PostSvc: PostService, ModSvc: ModerationService>(
+    State((tera, post_svc, mod_svc, _, _)): State<RevisionRoutesState<PostSvc, ModSvc>>,
+    Path((post_id, revision_id)): Path<(Uuid, Uuid)>,
+    session: Session,
+) -> response::Result<Html<String>> {
+    if !mod_svc
+        .is_moderator(session.user_id)
+        .await
+        .map_err(AppError::from)?
+    {
+        return Err(AppError::forbidden("error-restore-not-moderator").into());
+    }
+
+    let Some(post) = post_svc
+        .restore_revision(post_id, revision_id, session.user_id)
+        .await
+        .inspect_err(ert!())
+        .map_err(AppError::from)?
+    else {
+        return Err(AppError::not_found("error-revision-not-found").into());
+    };
+    render_revisions(&tera, &post_svc, post).await
+}
+
+async fn render_revisions<PostSvc: PostService>(
+    tera: &RwLock<Tera>,
+    post_svc: &PostSvc,
+    post: Post,
+) -> response::Result<Html<String>> {
+    let revisions = post_svc.revisions(post.id).await.map_err(AppError::from)?;
+    let history = RevisionView::history(revisions, &post);
+
+    let view = Revisions {
+        post: &post,
+        history: &history,
+    };
+    let body = templating::render(&*tera.read().await, &view)
+        .inspect_err(ert!())
+        .map_err(AppError::from)?;
+    Ok(Html(body))
+}
+
+type RevisionRoutesState<P, M> = (
+    Arc<RwLock<Tera>>,
+    P,
+    M,
+    ContentFilterChain,
+    deadpool_lapin::Pool,
+);
+
+/// Mounted next to the routes in `routes::posts`.
+pub fn router<PostSvc: PostService, ModSvc: ModerationService>()
+-> Router<RevisionRoutesState<PostSvc, ModSvc>> {
+    Router::new()
+        .route(
+            "/{id}/edit",
+            get(edit_form::<PostSvc, ModSvc>).post(edit_post::<PostSvc, ModSvc>),
+        )
+        .route("/{id}/revisions", get(revisions::<PostSvc, ModSvc>))
+        .route(
+            "/{id}/revisions/{revision_id}/restore",
+            post(restore_revision::<PostSvc, ModSvc>),
+        )
+}
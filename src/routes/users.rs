@@ -1,58 +1,75 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use axum::{
-    extract::State,
+    Form, RequestExt, Router,
+    extract::{Path, Query, State},
     response,
-    routing::{get, MethodRouter},
-    Form, RequestExt,
+    routing::{get, post},
 };
 use tera::Tera;
 use tokio::sync::RwLock;
 
+use crate::background::posts_broker::PostsSubscriptionManager;
+use crate::middleware::htmx::{HxPage, HxRequest, HxTrigger};
+use crate::middleware::session::SignIn;
+use crate::models::notification::{NewNotification, NotificationKind};
+use crate::services::announce;
+use crate::services::follows::FollowService;
+use crate::services::notifications::NotificationService;
 use crate::services::users::UserService;
-use crate::{models, AppError};
+use crate::views::users::{FollowButton, Follows, UserCreated, UserList};
+use crate::{AppError, models, templating};
 
 use tracing::Instrument;
 
-async fn get_users<UserSvc: UserService>(
-    State((usersvc, tera)): State<UserRoutesState<UserSvc>>,
-    _req: axum::extract::Request,
-) -> response::Result<axum::response::Html<String>> {
+async fn get_users<
+    UserSvc: UserService,
+    FollowSvc: FollowService,
+    NotifySvc: NotificationService,
+>(
+    State((usersvc, tera, followsvc, _, _, _)): State<
+        UserRoutesState<UserSvc, FollowSvc, NotifySvc>,
+    >,
+    hx: HxRequest,
+    Query(list): Query<models::user::ListUsers>,
+    Query(acting): Query<models::MaybeActingUser>,
+) -> response::Result<HxPage> {
     let users = usersvc
-        .get_users(0, 200)
+        .get_users(&list, None, 200)
         .in_current_span()
         .await
         .map_err(AppError::from)?;
-
-    Ok(response::Html(
-        tera.read()
+    let followed = match acting.user_id {
+        Some(user_id) => followsvc
+            .following_ids(user_id)
             .await
-            .render(
-                "users/get.html",
-                &tera::Context::from_value(
-                    serde_json::to_value(serde_json::json!({"users": users}))
-                        .map_err(AppError::from)?,
-                )
-                .map_err(AppError::from)?,
-            )
             .map_err(AppError::from)?,
-    ))
+        None => HashSet::new(),
+    };
+
+    let view = UserList {
+        users: &users,
+        acting_user_id: acting.user_id,
+        followed: &followed,
+    };
+    Ok(hx.render(&*tera.read().await, "title-users", &view)?)
 }
 
-async fn create_user<UserSvc: UserService>(
-    State((usersvc, tera)): State<UserRoutesState<UserSvc>>,
+async fn create_user<
+    UserSvc: UserService,
+    FollowSvc: FollowService,
+    NotifySvc: NotificationService,
+>(
+    State((usersvc, tera, _, _, _, _)): State<UserRoutesState<UserSvc, FollowSvc, NotifySvc>>,
     // State(tera): State<Tera>,
     // Form(payload): Form<models::user::CreateUser>,
     req: axum::extract::Request,
-) -> response::Result<response::Html<String>> {
+) -> response::Result<(SignIn, HxTrigger, response::Html<String>)> {
     let Form(payload): Form<models::user::CreateUser> = req.extract().await?;
 
     if payload.email.is_empty() {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            response::Html("email invalid".to_owned()),
-        )
-            .into());
+        return Err(AppError::validation("error-email-invalid").into());
     }
 
     let user = usersvc
@@ -60,20 +77,170 @@ async fn create_user<UserSvc: UserService>(
         .await
         .map_err(AppError::from)?;
 
+    let view = UserCreated {
+        id: user.id,
+        email: &user.email,
+    };
+    // the browser acts as the user it created from now on; the event
+    // refreshes the user list of the start page
+    Ok((
+        SignIn(user.id),
+        HxTrigger::event("userCreated"),
+        response::Html(templating::render(&*tera.read().await, &view).map_err(AppError::from)?),
+    ))
+}
+
+async fn follow<UserSvc: UserService, FollowSvc: FollowService, NotifySvc: NotificationService>(
+    State((_, tera, followsvc, sub_mgr, notifysvc, rmq_conn_pool)): State<
+        UserRoutesState<UserSvc, FollowSvc, NotifySvc>,
+    >,
+    Path(followee): Path<i32>,
+    Form(f): Form<models::ActingUser>,
+) -> response::Result<response::Html<String>> {
+    if f.user_id == followee {
+        return Err(AppError::validation("error-follow-self").into());
+    }
+
+    let followed = followsvc
+        .follow(f.user_id, followee)
+        .await
+        .map_err(AppError::from)?;
+    refresh_live_feeds(&followsvc, &sub_mgr, f.user_id).await?;
+
+    if followed {
+        let new = vec![NewNotification {
+            user_id: followee,
+            kind: NotificationKind::Follow,
+            actor_id: f.user_id,
+            post_id: None,
+        }];
+        announce::deliver(&notifysvc, &rmq_conn_pool, new).await;
+    }
+
+    render_follow_button(&tera, followee, true).await
+}
+
+async fn unfollow<
+    UserSvc: UserService,
+    FollowSvc: FollowService,
+    NotifySvc: NotificationService,
+>(
+    State((_, tera, followsvc, sub_mgr, _, _)): State<
+        UserRoutesState<UserSvc, FollowSvc, NotifySvc>,
+    >,
+    Path(followee): Path<i32>,
+    Form(f): Form<models::ActingUser>,
+) -> response::Result<response::Html<String>> {
+    followsvc
+        .unfollow(f.user_id, followee)
+        .await
+        .map_err(AppError::from)?;
+    refresh_live_feeds(&followsvc, &sub_mgr, f.user_id).await?;
+
+    render_follow_button(&tera, followee, false).await
+}
+
+/// Open `Feed::Following` WebSockets of the user pick up the change right away.
+async fn refresh_live_feeds<FollowSvc: FollowService>(
+    followsvc: &FollowSvc,
+    sub_mgr: &PostsSubscriptionManager,
+    user_id: i32,
+) -> Result<(), AppError> {
+    let followees = followsvc.following_ids(user_id).await?;
+    sub_mgr.update_following(user_id, followees);
+    Ok(())
+}
+
+async fn render_follow_button(
+    tera: &RwLock<Tera>,
+    user_id: i32,
+    following: bool,
+) -> response::Result<response::Html<String>> {
+    let view = FollowButton { user_id, following };
+    Ok(response::Html(
+        templating::render(&*tera.read().await, &view).map_err(AppError::from)?,
+    ))
+}
+
+async fn followers<
+    UserSvc: UserService,
+    FollowSvc: FollowService,
+    NotifySvc: NotificationService,
+>(
+    State((_, tera, followsvc, _, _, _)): State<UserRoutesState<UserSvc, FollowSvc, NotifySvc>>,
+    Path(user_id): Path<i32>,
+) -> response::Result<response::Html<String>> {
+    let users = followsvc
+        .followers(user_id)
+        .in_current_span()
+        .await
+        .map_err(AppError::from)?;
+    render_follows(&tera, user_id, "followers", users).await
+}
+
+async fn following<
+    UserSvc: UserService,
+    FollowSvc: FollowService,
+    NotifySvc: NotificationService,
+>(
+    State((_, tera, followsvc, _, _, _)): State<UserRoutesState<UserSvc, FollowSvc, NotifySvc>>,
+    Path(user_id): Path<i32>,
+) -> response::Result<response::Html<String>> {
+    let users = followsvc
+        .following(user_id)
+        .in_current_span()
+        .await
+        .map_err(AppError::from)?;
+    render_follows(&tera, user_id, "following", users).await
+}
+
+async fn render_follows(
+    tera: &RwLock<Tera>,
+    user_id: i32,
+    direction: &str,
+    users: Vec<models::user::User>,
+) -> response::Result<response::Html<String>> {
+    let view = Follows {
+        user_id,
+        direction,
+        users: &users,
+    };
     Ok(response::Html(
-        tera.read()
-            .await
-            .render(
-                "users/create.html",
-                &tera::Context::from_value(serde_json::to_value(user).map_err(AppError::from)?)
-                    .map_err(AppError::from)?,
-            )
-            .map_err(AppError::from)?,
+        templating::render(&*tera.read().await, &view).map_err(AppError::from)?,
     ))
 }
 
-type UserRoutesState<T> = (T, Arc<RwLock<Tera>>);
+type UserRoutesState<T, F, N> = (
+    T,
+    Arc<RwLock<Tera>>,
+    F,
+    Arc<PostsSubscriptionManager>,
+    N,
+    deadpool_lapin::Pool,
+);
 
-pub fn router<UserSvc: UserService>() -> MethodRouter<UserRoutesState<UserSvc>> {
-    get(get_users::<UserSvc>).post(create_user::<UserSvc>)
+pub fn router<UserSvc: UserService, FollowSvc: FollowService, NotifySvc: NotificationService>()
+-> Router<UserRoutesState<UserSvc, FollowSvc, NotifySvc>> {
+    Router::new()
+        .route(
+            "/",
+            get(get_users::<UserSvc, FollowSvc, NotifySvc>)
+                .post(create_user::<UserSvc, FollowSvc, NotifySvc>),
+        )
+        .route(
+            "/{id}/follow",
+            post(follow::<UserSvc, FollowSvc, NotifySvc>),
+        )
+        .route(
+            "/{id}/unfollow",
+            post(unfollow::<UserSvc, FollowSvc, NotifySvc>),
+        )
+        .route(
+            "/{id}/followers",
+            get(followers::<UserSvc, FollowSvc, NotifySvc>),
+        )
+        .route(
+            "/{id}/following",
+            get(following::<UserSvc, FollowSvc, NotifySvc>),
+        )
 }
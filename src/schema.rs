@@ -1,22 +1,181 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    follows (follower_id, followee_id) {
+        follower_id -> Int4,
+        followee_id -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    moderation_actions (id) {
+        id -> Uuid,
+        moderator_id -> Nullable<Int4>,
+        #[max_length = 16]
+        kind -> Varchar,
+        post_id -> Nullable<Uuid>,
+        target_user_id -> Nullable<Int4>,
+        reason -> Nullable<Text>,
+        suspended_until -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    notifications (id) {
+        id -> Uuid,
+        user_id -> Int4,
+        #[max_length = 16]
+        kind -> Varchar,
+        actor_id -> Int4,
+        post_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        read_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    post_attachments (id) {
+        id -> Uuid,
+        post_id -> Uuid,
+        #[max_length = 32]
+        content_type -> Varchar,
+        byte_size -> Int4,
+        width -> Int4,
+        height -> Int4,
+        blob_key -> Text,
+        thumb_key -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    post_reactions (post_id, user_id, reaction) {
+        post_id -> Uuid,
+        user_id -> Int4,
+        #[max_length = 16]
+        reaction -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    post_reports (id) {
+        id -> Uuid,
+        post_id -> Uuid,
+        reporter_id -> Int4,
+        reason -> Text,
+        created_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    post_revisions (id) {
+        id -> Uuid,
+        post_id -> Uuid,
+        post_content -> Text,
+        tags -> Array<Nullable<Text>>,
+        edited_by -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
     posts (id) {
         id -> Uuid,
         user_id -> Int4,
         post_content -> Text,
         tags -> Array<Nullable<Text>>,
+        post_content_tsv -> Tsvector,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        parent_id -> Nullable<Uuid>,
+        root_id -> Nullable<Uuid>,
+        reply_count -> Int4,
+        post_content_html -> Nullable<Text>,
+        #[max_length = 16]
+        status -> Varchar,
+        publish_at -> Nullable<Timestamptz>,
+        edited_at -> Nullable<Timestamptz>,
+        hidden_at -> Nullable<Timestamptz>,
+        held_reason -> Nullable<Text>,
     }
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
+    rate_limits (key) {
+        key -> Text,
+        tat -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::*;
+
     users (id) {
         id -> Int4,
         #[max_length = 320]
         email -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        #[max_length = 32]
+        handle -> Nullable<Varchar>,
+        #[max_length = 64]
+        avatar_hash -> Nullable<Varchar>,
+        is_moderator -> Bool,
+        suspended_until -> Nullable<Timestamptz>,
     }
 }
 
+diesel::joinable!(notifications -> posts (post_id));
+diesel::joinable!(post_attachments -> posts (post_id));
+diesel::joinable!(post_reactions -> posts (post_id));
+diesel::joinable!(post_reactions -> users (user_id));
+diesel::joinable!(post_reports -> posts (post_id));
+diesel::joinable!(post_reports -> users (reporter_id));
+diesel::joinable!(post_revisions -> posts (post_id));
+diesel::joinable!(post_revisions -> users (edited_by));
 diesel::joinable!(posts -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(posts, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    follows,
+    moderation_actions,
+    notifications,
+    post_attachments,
+    post_reactions,
+    post_reports,
+    post_revisions,
+    posts,
+    rate_limits,
+    users,
+);
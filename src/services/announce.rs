@@ -0,0 +1,82 @@
+//! Telling people about what happened: the live feeds through the posts
+//! broker and the notifications of the users concerned.
+use macros::ert;
+use tracing::error;
+
+use crate::background::posts_broker::{self, PostsEvent};
+use crate::models::attachment::Attachment;
+use crate::models::notification::{NewNotification, NotificationKind};
+use crate::models::post::Post;
+use crate::services::notifications::{NotificationService, NotificationServiceDb};
+
+/// Stores `new` and pushes every stored notification to the open WebSockets
+/// of its recipient. Notifying is best effort and never fails the action
+/// that caused it.
+pub async fn deliver<NotifySvc: NotificationService>(
+    notifysvc: &NotifySvc,
+    rmq_conn_pool: &deadpool_lapin::Pool,
+    new: Vec<NewNotification>,
+) {
+    let Ok(stored) = notifysvc.notify(new).await.inspect_err(ert!()) else {
+        return;
+    };
+    for notification in stored {
+        let unread = notifysvc
+            .unread_count(notification.user_id)
+            .await
+            .inspect_err(ert!())
+            .unwrap_or_default();
+        let _ = posts_broker::publish(
+            rmq_conn_pool,
+            &PostsEvent::Notification {
+                notification,
+                unread,
+            },
+        )
+        .await
+        .inspect_err(ert!());
+    }
+}
+
+/// Tells everyone about a post that just went out: the live feeds through
+/// the broker and the users it mentions. `create_post`, drafts published by
+/// hand and the scheduler all go through here.
+pub async fn announce_post(
+    rmq_conn_pool: &deadpool_lapin::Pool,
+    notify_svc: &NotificationServiceDb,
+    post: &Post,
+    attachments: Vec<Attachment>,
+) -> anyhow::Result<()> {
+    posts_broker::publish(
+        rmq_conn_pool,
+        &PostsEvent::Created {
+            post: post.clone(),
+            attachments,
+        },
+    )
+    .await?;
+
+    let mentions = mention_notifications(notify_svc, post).await;
+    deliver(notify_svc, rmq_conn_pool, mentions).await;
+    Ok(())
+}
+
+/// One `mention` notification per user `@handle`d in `post`.
+pub async fn mention_notifications(
+    notify_svc: &NotificationServiceDb,
+    post: &Post,
+) -> Vec<NewNotification> {
+    notify_svc
+        .resolve_mentions(&post.post_content)
+        .await
+        .inspect_err(ert!())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|user_id| NewNotification {
+            user_id,
+            kind: NotificationKind::Mention,
+            actor_id: post.user_id,
+            post_id: Some(post.id),
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+use std::io::Cursor;
+
+use bytes::Bytes;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use macros::ert;
+use tracing::error;
+use uuid::Uuid;
+
+use super::blobs::BlobStore;
+use crate::i18n::Message;
+use crate::models::attachment::{Attachment, StoredImage};
+
+pub const MAX_ATTACHMENT_BYTES: usize = 5 * 1024 * 1024;
+pub const MAX_ATTACHMENTS: usize = 4;
+
+const ALLOWED_FORMATS: [ImageFormat; 3] = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP];
+const MAX_DIMENSION: u32 = 8192;
+const THUMB_SIZE: u32 = 320;
+
+/// Why an upload is not accepted as an image. Shown to the user.
+#[derive(Debug)]
+pub enum ImageRejected {
+    TooLarge,
+    UnsupportedType,
+    Invalid(String),
+}
+
+impl std::fmt::Display for ImageRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge => write!(
+                f,
+                "images can be at most {} MiB",
+                MAX_ATTACHMENT_BYTES / 1024 / 1024
+            ),
+            Self::UnsupportedType => write!(f, "images must be PNG, JPEG or WebP"),
+            Self::Invalid(e) => write!(f, "not a valid image: {e}"),
+        }
+    }
+}
+
+impl ImageRejected {
+    pub fn invalid(e: image::ImageError) -> Self {
+        Self::Invalid(e.to_string())
+    }
+
+    /// What the user is told, in their language.
+    pub fn message(&self) -> Message {
+        match self {
+            Self::TooLarge => {
+                Message::new("error-image-too-large").arg("mib", MAX_ATTACHMENT_BYTES / 1024 / 1024)
+            }
+            Self::UnsupportedType => Message::new("error-image-unsupported"),
+            Self::Invalid(e) => Message::new("error-image-invalid").arg("error", e.as_str()),
+        }
+    }
+}
+
+/// A validated upload, re-encoded without its metadata, and its thumbnail.
+pub struct ProcessedImage {
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    image: Bytes,
+    thumb: Bytes,
+}
+
+/// Checks that `upload` is an image of an allowed type by its magic bytes,
+/// not by what the client claims, and decodes it with the EXIF orientation
+/// applied. Encoding the result again keeps only the pixels, so EXIF data
+/// like the GPS position is dropped.
+///
+/// CPU bound, like everything touching pixels here: run it on a blocking
+/// thread.
+pub fn decode_image(upload: &[u8]) -> Result<(DynamicImage, ImageFormat), ImageRejected> {
+    if upload.len() > MAX_ATTACHMENT_BYTES {
+        return Err(ImageRejected::TooLarge);
+    }
+    let format = image::guess_format(upload).map_err(|_| ImageRejected::UnsupportedType)?;
+    if !ALLOWED_FORMATS.contains(&format) {
+        return Err(ImageRejected::UnsupportedType);
+    }
+
+    let mut reader = ImageReader::with_format(Cursor::new(upload), format);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(ImageRejected::invalid)?;
+    let orientation = decoder.orientation().map_err(ImageRejected::invalid)?;
+    let mut img = DynamicImage::from_decoder(decoder).map_err(ImageRejected::invalid)?;
+    img.apply_orientation(orientation);
+    Ok((img, format))
+}
+
+/// Decodes an attachment and re-encodes it and its thumbnail.
+pub fn process_image(upload: &[u8]) -> Result<ProcessedImage, ImageRejected> {
+    let (img, format) = decode_image(upload)?;
+    Ok(ProcessedImage {
+        format,
+        width: img.width(),
+        height: img.height(),
+        image: encode(&img, format).map_err(ImageRejected::invalid)?,
+        thumb: encode(&img.thumbnail(THUMB_SIZE, THUMB_SIZE), format)
+            .map_err(ImageRejected::invalid)?,
+    })
+}
+
+pub fn encode(img: &DynamicImage, format: ImageFormat) -> image::ImageResult<Bytes> {
+    let mut out = Cursor::new(Vec::new());
+    match format {
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8()).write_to(&mut out, format)?,
+        _ => img.write_to(&mut out, format)?,
+    }
+    Ok(out.into_inner().into())
+}
+
+/// Puts the images and their thumbnails into `blobs`. Either all of them
+/// are stored or, on error, none.
+pub async fn store_images<Blobs: BlobStore>(
+    blobs: &Blobs,
+    images: Vec<ProcessedImage>,
+) -> anyhow::Result<Vec<StoredImage>> {
+    let mut stored = Vec::with_capacity(images.len());
+    for img in images {
+        let id = Uuid::now_v7();
+        let ext = img.format.extensions_str()[0];
+        let s = StoredImage {
+            id,
+            content_type: img.format.to_mime_type().to_owned(),
+            byte_size: img.image.len() as i32,
+            width: img.width as i32,
+            height: img.height as i32,
+            blob_key: format!("attachments/{id}.{ext}"),
+            thumb_key: format!("attachments/{id}.thumb.{ext}"),
+        };
+
+        let res = match blobs.put(&s.blob_key, img.image).await {
+            Ok(()) => blobs.put(&s.thumb_key, img.thumb).await,
+            Err(e) => Err(e),
+        };
+        // pushed first so a half stored image is cleaned up too
+        stored.push(s);
+        if let Err(e) = res {
+            discard_images(blobs, &stored).await;
+            return Err(e);
+        }
+    }
+    Ok(stored)
+}
+
+/// Best effort removal of images whose post never made it to the database.
+pub async fn discard_images<Blobs: BlobStore>(blobs: &Blobs, images: &[StoredImage]) {
+    for s in images {
+        let _ = blobs.delete(&s.blob_key).await.inspect_err(ert!());
+        let _ = blobs.delete(&s.thumb_key).await.inspect_err(ert!());
+    }
+}
+
+/// Best effort removal of the images of a deleted post.
+pub async fn discard_attachments<Blobs: BlobStore>(blobs: &Blobs, attachments: &[Attachment]) {
+    for a in attachments {
+        let _ = blobs.delete(&a.blob_key).await.inspect_err(ert!());
+        let _ = blobs.delete(&a.thumb_key).await.inspect_err(ert!());
+    }
+}
+
+/// The `Content-Type` of a blob, from the extension of its key.
+pub fn content_type(key: &str) -> &'static str {
+    ImageFormat::from_path(key)
+        .map(|f| f.to_mime_type())
+        .unwrap_or("application/octet-stream")
+}
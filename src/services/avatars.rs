@@ -0,0 +1,113 @@
+use bytes::Bytes;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use sha2::{Digest, Sha256};
+
+use super::attachments::{self, ImageRejected};
+use super::blobs::BlobStore;
+
+/// The square sizes, in pixels, every avatar is stored at.
+pub const AVATAR_SIZES: [u32; 3] = [32, 64, 128];
+
+/// An uploaded avatar, resized to each of `AVATAR_SIZES`.
+pub struct ProcessedAvatar {
+    /// hex sha256 of the upload, the same picture always gets the same keys
+    pub hash: String,
+    sizes: Vec<(u32, Bytes)>,
+}
+
+pub fn avatar_key(hash: &str, size: u32) -> String {
+    format!("avatars/{hash}/{size}.png")
+}
+
+/// Validates the upload like an attachment and crops it to squares of
+/// every avatar size.
+///
+/// CPU bound, run it on a blocking thread.
+pub fn process_avatar(upload: &[u8]) -> Result<ProcessedAvatar, ImageRejected> {
+    let (img, _) = attachments::decode_image(upload)?;
+    let sizes = AVATAR_SIZES
+        .iter()
+        .map(|&size| {
+            let resized = img.resize_to_fill(size, size, FilterType::Lanczos3);
+            attachments::encode(&resized, ImageFormat::Png)
+                .map(|png| (size, png))
+                .map_err(ImageRejected::invalid)
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(ProcessedAvatar {
+        hash: hex(&Sha256::digest(upload)),
+        sizes,
+    })
+}
+
+/// Puts every size of `avatar` into `blobs`. Storing the same avatar again
+/// overwrites identical content.
+pub async fn store_avatar<Blobs: BlobStore>(
+    blobs: &Blobs,
+    avatar: ProcessedAvatar,
+) -> anyhow::Result<String> {
+    for (size, png) in avatar.sizes {
+        blobs.put(&avatar_key(&avatar.hash, size), png).await?;
+    }
+    Ok(avatar.hash)
+}
+
+/// A PNG identicon for users without an avatar: a mirrored 5x5 pattern in a
+/// color, both derived from the user id so they never change.
+pub fn identicon(user_id: i32, size: u32) -> anyhow::Result<Bytes> {
+    const CELLS: u32 = 5;
+
+    let digest = Sha256::digest(format!("identicon:{user_id}"));
+    let color = hsl_to_rgb(
+        f32::from(u16::from_be_bytes([digest[0], digest[1]])) / f32::from(u16::MAX),
+        0.55,
+        0.5,
+    );
+    let background = Rgb([240, 240, 240]);
+
+    // half a cell of margin on each side
+    let cell = size as f32 / (CELLS + 1) as f32;
+    let on = |col: u32, row: u32| {
+        let col = col.min(CELLS - 1 - col);
+        let bit = row * 3 + col;
+        digest[2 + (bit / 8) as usize] >> (bit % 8) & 1 == 1
+    };
+
+    let img = RgbImage::from_fn(size, size, |x, y| {
+        let (cx, cy) = (x as f32 / cell - 0.5, y as f32 / cell - 0.5);
+        if cx < 0.0 || cy < 0.0 || cx >= CELLS as f32 || cy >= CELLS as f32 {
+            return background;
+        }
+        if on(cx as u32, cy as u32) {
+            color
+        } else {
+            background
+        }
+    });
+    Ok(attachments::encode(
+        &DynamicImage::ImageRgb8(img),
+        ImageFormat::Png,
+    )?)
+}
+
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> Rgb<u8> {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h6 = h * 6.0;
+    let x = c * (1.0 - (h6 % 2.0 - 1.0).abs());
+    let (r, g, b) = match h6 as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    let to_u8 = |v: f32| ((v + m) * 255.0).round() as u8;
+    Rgb([to_u8(r), to_u8(g), to_u8(b)])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
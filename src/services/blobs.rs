@@ -0,0 +1,168 @@
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, anyhow};
+use bytes::Bytes;
+use object_store::ObjectStore;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+
+use super::Svc;
+use crate::config::{BlobBackend, BlobsCfg};
+
+/// Where uploaded files live. Keys are `/` separated relative paths like
+/// `attachments/<id>.png`, picked by the app when storing.
+pub trait BlobStore<E = anyhow::Error>: Svc {
+    fn put(&self, key: &str, content: Bytes) -> impl Future<Output = Result<(), E>> + Send;
+    /// `None` when nothing is stored under `key`.
+    fn get(&self, key: &str) -> impl Future<Output = Result<Option<Bytes>, E>> + Send;
+    /// Deleting a missing key is not an error.
+    fn delete(&self, key: &str) -> impl Future<Output = Result<(), E>> + Send;
+}
+
+/// Blobs as files below a directory on the local disk.
+#[derive(Clone)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl Svc for LocalBlobStore {}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let rel = Path::new(key);
+        if key.is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(anyhow!("invalid blob key `{key}`"));
+        }
+        Ok(self.root.join(rel))
+    }
+}
+
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, content: Bytes) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        // readers never see a half written file
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, &content).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        // keys come in through `/blobs/{*key}`, a bad one is just not there
+        let Ok(path) = self.path(key) else {
+            return Ok(None);
+        };
+        match tokio::fs::read(path).await {
+            Ok(content) => Ok(Some(content.into())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Blobs in a bucket of S3 or anything speaking its API, e.g. the MinIO
+/// container in `docker-compose.yml`.
+#[derive(Clone)]
+pub struct S3BlobStore {
+    bucket: Arc<AmazonS3>,
+}
+
+impl Svc for S3BlobStore {}
+
+impl S3BlobStore {
+    pub fn new(bucket: AmazonS3) -> Self {
+        Self {
+            bucket: Arc::new(bucket),
+        }
+    }
+}
+
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, content: Bytes) -> anyhow::Result<()> {
+        self.bucket.put(&key.into(), content.into()).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        match self.bucket.get(&key.into()).await {
+            Ok(res) => Ok(Some(res.bytes().await?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self.bucket.delete(&key.into()).await {
+            Err(e) if !matches!(e, object_store::Error::NotFound { .. }) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The blob store picked by `blobs.backend` in the app config.
+#[derive(Clone)]
+pub enum AppBlobStore {
+    Local(LocalBlobStore),
+    S3(S3BlobStore),
+}
+
+impl Svc for AppBlobStore {}
+
+impl AppBlobStore {
+    pub fn from_cfg(cfg: &BlobsCfg) -> anyhow::Result<Self> {
+        Ok(match cfg.backend {
+            BlobBackend::Local => Self::Local(LocalBlobStore::new(&cfg.dir)),
+            BlobBackend::S3 => {
+                let bucket = cfg
+                    .s3_bucket
+                    .as_deref()
+                    .context("the S3 blob backend needs blobs.s3_bucket")?;
+                let mut b = AmazonS3Builder::from_env().with_bucket_name(bucket);
+                if let Some(endpoint) = &cfg.s3_endpoint {
+                    b = b
+                        .with_endpoint(endpoint)
+                        .with_allow_http(endpoint.starts_with("http://"));
+                }
+                Self::S3(S3BlobStore::new(b.build()?))
+            }
+        })
+    }
+}
+
+impl BlobStore for AppBlobStore {
+    async fn put(&self, key: &str, content: Bytes) -> anyhow::Result<()> {
+        match self {
+            Self::Local(s) => s.put(key, content).await,
+            Self::S3(s) => s.put(key, content).await,
+        }
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        match self {
+            Self::Local(s) => s.get(key).await,
+            Self::S3(s) => s.get(key).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Local(s) => s.delete(key).await,
+            Self::S3(s) => s.delete(key).await,
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use futures::future::BoxFuture;
+use linkify::{LinkFinder, LinkKind};
+use uuid::Uuid;
+
+use super::posts::PostService;
+use crate::config::ContentFilterCfg;
+use crate::i18n::Message;
+
+// longer windows look at the same posts anyway
+const MAX_WINDOW_SECS: u64 = 366 * 24 * 60 * 60;
+
+/// What a filter makes of a post. The reasons are shown to the author, or to
+/// the moderators for held posts, which are stored as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Reject(Message),
+    /// stored, but only published once a moderator approves it
+    Hold(String),
+}
+
+/// A post about to be stored, or new content for one.
+pub struct Candidate<'a> {
+    pub user_id: i32,
+    pub content: &'a str,
+    /// the stored post when a draft is published or a post edited
+    pub post_id: Option<Uuid>,
+}
+
+/// One check in a `ContentFilterChain`.
+pub trait ContentFilter: Send + Sync {
+    fn check<'a>(&'a self, post: &'a Candidate<'a>) -> BoxFuture<'a, anyhow::Result<Verdict>>;
+}
+
+/// Runs new posts through its filters before they are stored.
+#[derive(Clone)]
+pub struct ContentFilterChain {
+    filters: Arc<[Box<dyn ContentFilter>]>,
+}
+
+impl ContentFilterChain {
+    pub fn new(filters: Vec<Box<dyn ContentFilter>>) -> Self {
+        Self {
+            filters: filters.into(),
+        }
+    }
+
+    /// The filters configured in `cfg`, the ones not touching the database
+    /// first.
+    pub fn from_cfg<PostSvc: PostService>(cfg: &ContentFilterCfg, post_svc: PostSvc) -> Self {
+        let secs = |s: u64| Duration::seconds(s.min(MAX_WINDOW_SECS) as i64);
+        Self::new(vec![
+            Box::new(MaxLength {
+                max: cfg.max_length,
+            }),
+            Box::new(WordList::new(&cfg.blocked_words, false)),
+            Box::new(WordList::new(&cfg.review_words, true)),
+            Box::new(LinkLimit { max: cfg.max_links }),
+            Box::new(Duplicates {
+                post_svc: post_svc.clone(),
+                window: secs(cfg.duplicate_window_secs),
+            }),
+            Box::new(Quota {
+                post_svc,
+                max: cfg.quota_posts,
+                window: secs(cfg.quota_window_secs),
+            }),
+        ])
+    }
+
+    /// Runs every filter in order. The first rejection wins, otherwise the
+    /// post is held if any filter wants it held.
+    pub async fn check(&self, post: &Candidate<'_>) -> anyhow::Result<Verdict> {
+        let mut verdict = Verdict::Allow;
+        for filter in self.filters.iter() {
+            match filter.check(post).await? {
+                Verdict::Allow => {}
+                reject @ Verdict::Reject(_) => return Ok(reject),
+                hold @ Verdict::Hold(_) if verdict == Verdict::Allow => verdict = hold,
+                Verdict::Hold(_) => {}
+            }
+        }
+        Ok(verdict)
+    }
+}
+
+/// Rejects posts longer than `max` characters.
+pub struct MaxLength {
+    pub max: usize,
+}
+
+impl ContentFilter for MaxLength {
+    fn check<'a>(&'a self, post: &'a Candidate<'a>) -> BoxFuture<'a, anyhow::Result<Verdict>> {
+        let verdict = if post.content.chars().count() > self.max {
+            Verdict::Reject(Message::new("reject-too-long").arg("max", self.max))
+        } else {
+            Verdict::Allow
+        };
+        Box::pin(std::future::ready(Ok(verdict)))
+    }
+}
+
+/// Rejects, or with `hold` holds, posts containing any of a list of words.
+/// Matches whole words, ignoring case.
+pub struct WordList {
+    words: HashSet<String>,
+    hold: bool,
+}
+
+impl WordList {
+    pub fn new(words: &[String], hold: bool) -> Self {
+        Self {
+            words: words.iter().map(|w| w.trim().to_lowercase()).collect(),
+            hold,
+        }
+    }
+}
+
+impl ContentFilter for WordList {
+    fn check<'a>(&'a self, post: &'a Candidate<'a>) -> BoxFuture<'a, anyhow::Result<Verdict>> {
+        let found = post
+            .content
+            .split(|c: char| !c.is_alphanumeric())
+            .find(|w| !w.is_empty() && self.words.contains(&w.to_lowercase()));
+        let verdict = match found {
+            None => Verdict::Allow,
+            Some(w) if self.hold => Verdict::Hold(format!("contains `{w}`")),
+            Some(_) => Verdict::Reject(Message::new("reject-blocked-word")),
+        };
+        Box::pin(std::future::ready(Ok(verdict)))
+    }
+}
+
+/// Holds posts with more than `max` links, the usual shape of spam.
+pub struct LinkLimit {
+    pub max: usize,
+}
+
+impl ContentFilter for LinkLimit {
+    fn check<'a>(&'a self, post: &'a Candidate<'a>) -> BoxFuture<'a, anyhow::Result<Verdict>> {
+        let mut finder = LinkFinder::new();
+        finder.kinds(&[LinkKind::Url]);
+        let links = finder.links(post.content).count();
+        let verdict = if links > self.max {
+            Verdict::Hold(format!("{links} links, more than {}", self.max))
+        } else {
+            Verdict::Allow
+        };
+        Box::pin(std::future::ready(Ok(verdict)))
+    }
+}
+
+/// Rejects a post its author already made within `window`.
+pub struct Duplicates<PostSvc> {
+    pub post_svc: PostSvc,
+    pub window: Duration,
+}
+
+impl<PostSvc: PostService> ContentFilter for Duplicates<PostSvc> {
+    fn check<'a>(&'a self, post: &'a Candidate<'a>) -> BoxFuture<'a, anyhow::Result<Verdict>> {
+        Box::pin(async move {
+            let since = Utc::now() - self.window;
+            let same = self
+                .post_svc
+                .count_recent(post.user_id, since, Some(post.content), post.post_id)
+                .await?;
+            Ok(if same > 0 {
+                Verdict::Reject(Message::new("reject-duplicate"))
+            } else {
+                Verdict::Allow
+            })
+        })
+    }
+}
+
+/// Rejects posts of users who made `max` posts within `window` already.
+pub struct Quota<PostSvc> {
+    pub post_svc: PostSvc,
+    pub max: i64,
+    pub window: Duration,
+}
+
+impl<PostSvc: PostService> ContentFilter for Quota<PostSvc> {
+    fn check<'a>(&'a self, post: &'a Candidate<'a>) -> BoxFuture<'a, anyhow::Result<Verdict>> {
+        Box::pin(async move {
+            let since = Utc::now() - self.window;
+            let made = self
+                .post_svc
+                .count_recent(post.user_id, since, None, post.post_id)
+                .await?;
+            Ok(if made >= self.max {
+                Verdict::Reject(
+                    Message::new("reject-quota")
+                        .arg("max", self.max)
+                        .arg("minutes", self.window.num_minutes()),
+                )
+            } else {
+                Verdict::Allow
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn check(filter: impl ContentFilter, content: &str) -> Verdict {
+        let post = Candidate {
+            user_id: 1,
+            content,
+            post_id: None,
+        };
+        filter.check(&post).await.unwrap()
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn max_length_counts_characters() {
+        assert_eq!(check(MaxLength { max: 5 }, "ääääß").await, Verdict::Allow);
+        assert_eq!(
+            check(MaxLength { max: 5 }, "ääääßx").await,
+            Verdict::Reject(Message::new("reject-too-long").arg("max", 5usize))
+        );
+    }
+
+    #[tokio::test]
+    async fn word_list_matches_whole_words_ignoring_case() {
+        let blocked = || WordList::new(&words(&[" Casino "]), false);
+        let rejected = Verdict::Reject(Message::new("reject-blocked-word"));
+        assert_eq!(check(blocked(), "my CASINO!").await, rejected);
+        assert_eq!(check(blocked(), "the-casino-night").await, rejected);
+        assert_eq!(
+            check(blocked(), "casinos and occasion").await,
+            Verdict::Allow
+        );
+        assert_eq!(check(blocked(), "").await, Verdict::Allow);
+
+        let review = WordList::new(&words(&["crypto"]), true);
+        assert_eq!(
+            check(review, "Crypto, anyone?").await,
+            Verdict::Hold("contains `Crypto`".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn link_limit_holds_posts_over_the_limit() {
+        let two = "https://a.example and https://b.example/x";
+        assert_eq!(check(LinkLimit { max: 2 }, two).await, Verdict::Allow);
+        assert_eq!(
+            check(LinkLimit { max: 1 }, two).await,
+            Verdict::Hold("2 links, more than 1".into())
+        );
+        // bare domains and emails are no links
+        assert_eq!(
+            check(LinkLimit { max: 0 }, "a.example or me@a.example").await,
+            Verdict::Allow
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_prefers_rejections_over_holds() {
+        let chain = ContentFilterChain::new(vec![
+            Box::new(LinkLimit { max: 0 }),
+            Box::new(WordList::new(&words(&["crypto"]), true)),
+            Box::new(MaxLength { max: 30 }),
+            Box::new(WordList::new(&words(&["casino"]), false)),
+        ]);
+        let post = |content| Candidate {
+            user_id: 1,
+            content,
+            post_id: None,
+        };
+
+        let verdict = chain.check(&post("crypto at https://a.example")).await;
+        assert_eq!(
+            verdict.unwrap(),
+            Verdict::Hold("1 links, more than 0".into())
+        );
+        let verdict = chain.check(&post("crypto casino https://a.io")).await;
+        assert_eq!(
+            verdict.unwrap(),
+            Verdict::Reject(Message::new("reject-blocked-word"))
+        );
+        // the first rejection wins
+        let verdict = chain
+            .check(&post("casino casino casino casino casino"))
+            .await;
+        assert_eq!(
+            verdict.unwrap(),
+            Verdict::Reject(Message::new("reject-too-long").arg("max", 30usize))
+        );
+        assert_eq!(chain.check(&post("hello")).await.unwrap(), Verdict::Allow);
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::future::Future;
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::models::follow::*;
+use crate::models::user::User;
+use crate::schema;
+
+use super::{Pool, Svc};
+
+pub trait FollowService<E = anyhow::Error>: Svc {
+    /// Returns `false` if `follower` already followed `followee`.
+    fn follow(&self, follower: i32, followee: i32) -> impl Future<Output = Result<bool, E>> + Send;
+    /// Returns `false` if `follower` did not follow `followee`.
+    fn unfollow(
+        &self,
+        follower: i32,
+        followee: i32,
+    ) -> impl Future<Output = Result<bool, E>> + Send;
+    fn followers(&self, user: i32) -> impl Future<Output = Result<Vec<User>, E>> + Send;
+    fn following(&self, user: i32) -> impl Future<Output = Result<Vec<User>, E>> + Send;
+    fn following_ids(&self, user: i32) -> impl Future<Output = Result<HashSet<i32>, E>> + Send;
+}
+
+#[derive(Clone)]
+pub struct FollowServiceDb {
+    db: Pool,
+}
+
+impl Svc for FollowServiceDb {}
+
+impl FollowService<anyhow::Error> for FollowServiceDb {
+    async fn follow(&self, follower: i32, followee: i32) -> anyhow::Result<bool> {
+        use schema::follows::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let n = diesel::insert_into(follows)
+            .values(NewFollow {
+                follower_id: follower,
+                followee_id: followee,
+            })
+            .on_conflict_do_nothing()
+            .execute(&mut conn)
+            .await?;
+        Ok(n > 0)
+    }
+
+    async fn unfollow(&self, follower: i32, followee: i32) -> anyhow::Result<bool> {
+        use schema::follows::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let n = diesel::delete(follows.find((follower, followee)))
+            .execute(&mut conn)
+            .await?;
+        Ok(n > 0)
+    }
+
+    async fn followers(&self, user: i32) -> anyhow::Result<Vec<User>> {
+        use schema::follows::dsl as f;
+        use schema::users::dsl as u;
+
+        let mut conn = self.db.get().await?;
+        let us = u::users
+            .inner_join(f::follows.on(f::follower_id.eq(u::id)))
+            .filter(f::followee_id.eq(user))
+            .order(f::created_at.desc())
+            .select(User::as_select())
+            .load(&mut conn)
+            .await?;
+        Ok(us)
+    }
+
+    async fn following(&self, user: i32) -> anyhow::Result<Vec<User>> {
+        use schema::follows::dsl as f;
+        use schema::users::dsl as u;
+
+        let mut conn = self.db.get().await?;
+        let us = u::users
+            .inner_join(f::follows.on(f::followee_id.eq(u::id)))
+            .filter(f::follower_id.eq(user))
+            .order(f::created_at.desc())
+            .select(User::as_select())
+            .load(&mut conn)
+            .await?;
+        Ok(us)
+    }
+
+    async fn following_ids(&self, user: i32) -> anyhow::Result<HashSet<i32>> {
+        use schema::follows::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let ids: Vec<i32> = follows
+            .filter(follower_id.eq(user))
+            .select(followee_id)
+            .load(&mut conn)
+            .await?;
+        Ok(ids.into_iter().collect())
+    }
+}
+
+impl FollowServiceDb {
+    pub fn new(db: Pool) -> Self {
+        Self { db }
+    }
+}
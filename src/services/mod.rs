@@ -1,5 +1,14 @@
 use diesel_async::AsyncPgConnection;
 
+pub mod announce;
+pub mod attachments;
+pub mod avatars;
+pub mod blobs;
+pub mod content_filter;
+pub mod follows;
+pub mod moderation;
+pub mod notifications;
+pub mod posts;
 pub mod users;
 
 pub type Pool = diesel_async::pooled_connection::deadpool::Pool<AsyncPgConnection>;
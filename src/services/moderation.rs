@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::models::attachment::Attachment;
+use crate::models::moderation::*;
+use crate::models::post::{Post, PostStatus};
+use crate::schema;
+
+use super::{Pool, Svc};
+
+pub trait ModerationService<E = anyhow::Error>: Svc {
+    fn is_moderator(&self, user_id: i32) -> impl Future<Output = Result<bool, E>> + Send;
+    /// `Some` while the user is suspended and may not post.
+    fn suspended_until(
+        &self,
+        user_id: i32,
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>, E>> + Send;
+    /// Files a report, once per user while it is open. Returns `false` when
+    /// there is no such visible post.
+    fn report(&self, report: &ReportPost) -> impl Future<Output = Result<bool, E>> + Send;
+    /// Up to `limit` posts with open reports, the most reported first.
+    fn queue(&self, limit: i64) -> impl Future<Output = Result<Vec<ReportedPost>, E>> + Send;
+    /// Up to `limit` posts held by the content filters, oldest first.
+    fn held_posts(&self, limit: i64) -> impl Future<Output = Result<Vec<Post>, E>> + Send;
+    /// Publishes a held post as if it was made just now. Returns it with its
+    /// attachments, `None` when there is no such held post.
+    fn approve_post(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> impl Future<Output = Result<Option<(Post, Vec<Attachment>)>, E>> + Send;
+    /// The audit log, newest first.
+    fn recent_actions(
+        &self,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<ModerationAction>, E>> + Send;
+    /// Hides a post from everyone and closes its reports. `None` when there
+    /// is no such post or it is hidden already.
+    fn hide_post(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> impl Future<Output = Result<Option<Post>, E>> + Send;
+    /// Deletes a post with its replies. Returns it and its attachments, whose
+    /// blobs are left to the caller to delete. `None` when there is no such post.
+    fn delete_post(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> impl Future<Output = Result<Option<(Post, Vec<Attachment>)>, E>> + Send;
+    /// Closes the open reports of a post without acting on it. Returns
+    /// `false` when there were none.
+    fn dismiss_reports(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> impl Future<Output = Result<bool, E>> + Send;
+    /// Returns `false` when there is no such user.
+    fn suspend_user(
+        &self,
+        moderator: i32,
+        user_id: i32,
+        until: DateTime<Utc>,
+        reason: Option<&str>,
+    ) -> impl Future<Output = Result<bool, E>> + Send;
+}
+
+#[derive(Clone)]
+pub struct ModerationServiceDb {
+    db: Pool,
+}
+
+impl Svc for ModerationServiceDb {}
+
+impl ModerationService<anyhow::Error> for ModerationServiceDb {
+    async fn is_moderator(&self, user: i32) -> anyhow::Result<bool> {
+        use schema::users::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let moderator = users
+            .find(user)
+            .select(is_moderator)
+            .first(&mut conn)
+            .await
+            .optional()?;
+        Ok(moderator.unwrap_or(false))
+    }
+
+    async fn suspended_until(&self, user: i32) -> anyhow::Result<Option<DateTime<Utc>>> {
+        use schema::users::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let until = users
+            .find(user)
+            .filter(suspended_until.gt(diesel::dsl::now))
+            .select(suspended_until)
+            .first::<Option<DateTime<Utc>>>(&mut conn)
+            .await
+            .optional()?;
+        Ok(until.flatten())
+    }
+
+    async fn report(&self, report: &ReportPost) -> anyhow::Result<bool> {
+        use schema::post_reports::dsl::post_reports;
+        use schema::posts::dsl as p;
+
+        let mut conn = self.db.get().await?;
+        let exists = p::posts
+            .find(report.post_id)
+            .filter(p::status.eq(PostStatus::Published))
+            .filter(p::hidden_at.is_null())
+            .select(p::id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()?;
+        if exists.is_none() {
+            return Ok(false);
+        }
+
+        // reporting again while the first report is open changes nothing
+        diesel::insert_into(post_reports)
+            .values(report)
+            .on_conflict_do_nothing()
+            .execute(&mut conn)
+            .await?;
+        Ok(true)
+    }
+
+    async fn queue(&self, limit: i64) -> anyhow::Result<Vec<ReportedPost>> {
+        use schema::post_reports::dsl as r;
+        use schema::posts::dsl as p;
+
+        let mut conn = self.db.get().await?;
+        let most_reported: Vec<Uuid> = r::post_reports
+            .filter(r::resolved_at.is_null())
+            .group_by(r::post_id)
+            .order((
+                diesel::dsl::count_star().desc(),
+                diesel::dsl::min(r::created_at).asc(),
+            ))
+            .limit(limit)
+            .select(r::post_id)
+            .load(&mut conn)
+            .await?;
+
+        let mut reports: HashMap<Uuid, Vec<Report>> = HashMap::new();
+        for report in r::post_reports
+            .filter(r::resolved_at.is_null())
+            .filter(r::post_id.eq_any(&most_reported))
+            .order(r::created_at.asc())
+            .select(Report::as_select())
+            .load::<Report>(&mut conn)
+            .await?
+        {
+            reports.entry(report.post_id).or_default().push(report);
+        }
+        let mut posts: HashMap<Uuid, Post> = p::posts
+            .filter(p::id.eq_any(&most_reported))
+            .select(Post::as_select())
+            .load::<Post>(&mut conn)
+            .await?
+            .into_iter()
+            .map(|post| (post.id, post))
+            .collect();
+
+        Ok(most_reported
+            .into_iter()
+            .filter_map(|id| {
+                Some(ReportedPost {
+                    post: posts.remove(&id)?,
+                    reports: reports.remove(&id).unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    async fn held_posts(&self, limit: i64) -> anyhow::Result<Vec<Post>> {
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let held = posts
+            .filter(status.eq(PostStatus::Held))
+            .order(created_at.asc())
+            .limit(limit)
+            .select(Post::as_select())
+            .load(&mut conn)
+            .await?;
+        Ok(held)
+    }
+
+    async fn approve_post(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> anyhow::Result<Option<(Post, Vec<Attachment>)>> {
+        use schema::post_attachments::dsl as a;
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                let Some(post) =
+                    diesel::update(posts.find(post_id).filter(status.eq(PostStatus::Held)))
+                        .set((
+                            status.eq(PostStatus::Published),
+                            created_at.eq(diesel::dsl::now),
+                        ))
+                        .returning(Post::as_returning())
+                        .get_result(conn)
+                        .await
+                        .optional()?
+                else {
+                    return Ok(None);
+                };
+                if let Some(parent) = post.parent_id {
+                    diesel::update(posts.find(parent))
+                        .set(reply_count.eq(reply_count + 1))
+                        .execute(conn)
+                        .await?;
+                }
+                let attachments = a::post_attachments
+                    .filter(a::post_id.eq(post_id))
+                    .select(Attachment::as_select())
+                    .load(conn)
+                    .await?;
+
+                record(
+                    conn,
+                    NewModerationAction {
+                        moderator_id: moderator,
+                        kind: ModerationKind::Approve,
+                        post_id: Some(post_id),
+                        target_user_id: Some(post.user_id),
+                        reason,
+                        suspended_until: None,
+                    },
+                )
+                .await?;
+                Ok(Some((post, attachments)))
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn recent_actions(&self, limit: i64) -> anyhow::Result<Vec<ModerationAction>> {
+        use schema::moderation_actions::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let actions = moderation_actions
+            .order((created_at.desc(), id.desc()))
+            .limit(limit)
+            .select(ModerationAction::as_select())
+            .load(&mut conn)
+            .await?;
+        Ok(actions)
+    }
+
+    async fn hide_post(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> anyhow::Result<Option<Post>> {
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                let Some(post) = diesel::update(posts.find(post_id).filter(hidden_at.is_null()))
+                    .set(hidden_at.eq(diesel::dsl::now))
+                    .returning(Post::as_returning())
+                    .get_result(conn)
+                    .await
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+                // like a deleted reply, a hidden one is not counted
+                if let Some(parent) = post.parent_id
+                    && post.status == PostStatus::Published
+                {
+                    diesel::update(posts.find(parent))
+                        .set(reply_count.eq(reply_count - 1))
+                        .execute(conn)
+                        .await?;
+                }
+
+                resolve_reports(conn, post_id).await?;
+                record(
+                    conn,
+                    NewModerationAction {
+                        moderator_id: moderator,
+                        kind: ModerationKind::Hide,
+                        post_id: Some(post_id),
+                        target_user_id: Some(post.user_id),
+                        reason,
+                        suspended_until: None,
+                    },
+                )
+                .await?;
+                Ok(Some(post))
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn delete_post(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> anyhow::Result<Option<(Post, Vec<Attachment>)>> {
+        use schema::post_attachments::dsl as a;
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                // replies go with the post, they carry no attachments
+                let attachments = a::post_attachments
+                    .filter(a::post_id.eq(post_id))
+                    .select(Attachment::as_select())
+                    .load(conn)
+                    .await?;
+                let Some(post) = diesel::delete(posts.find(post_id))
+                    .returning(Post::as_returning())
+                    .get_result(conn)
+                    .await
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+                // held replies were never counted, hidden ones no longer are
+                if let Some(parent) = post.parent_id
+                    && post.status == PostStatus::Published
+                    && post.hidden_at.is_none()
+                {
+                    diesel::update(posts.find(parent))
+                        .set(reply_count.eq(reply_count - 1))
+                        .execute(conn)
+                        .await?;
+                }
+
+                record(
+                    conn,
+                    NewModerationAction {
+                        moderator_id: moderator,
+                        kind: ModerationKind::Delete,
+                        post_id: Some(post_id),
+                        target_user_id: Some(post.user_id),
+                        reason,
+                        suspended_until: None,
+                    },
+                )
+                .await?;
+                Ok(Some((post, attachments)))
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn dismiss_reports(
+        &self,
+        moderator: i32,
+        post_id: Uuid,
+        reason: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                if resolve_reports(conn, post_id).await? == 0 {
+                    return Ok(false);
+                }
+                record(
+                    conn,
+                    NewModerationAction {
+                        moderator_id: moderator,
+                        kind: ModerationKind::Dismiss,
+                        post_id: Some(post_id),
+                        target_user_id: None,
+                        reason,
+                        suspended_until: None,
+                    },
+                )
+                .await?;
+                Ok(true)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn suspend_user(
+        &self,
+        moderator: i32,
+        user: i32,
+        until: DateTime<Utc>,
+        reason: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        use schema::users::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                let updated = diesel::update(users.find(user))
+                    .set(suspended_until.eq(until))
+                    .execute(conn)
+                    .await?;
+                if updated == 0 {
+                    return Ok(false);
+                }
+                record(
+                    conn,
+                    NewModerationAction {
+                        moderator_id: moderator,
+                        kind: ModerationKind::Suspend,
+                        post_id: None,
+                        target_user_id: Some(user),
+                        reason,
+                        suspended_until: Some(until),
+                    },
+                )
+                .await?;
+                Ok(true)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+impl ModerationServiceDb {
+    pub fn new(db: Pool) -> Self {
+        Self { db }
+    }
+}
+
+async fn resolve_reports(conn: &mut AsyncPgConnection, reported: Uuid) -> anyhow::Result<usize> {
+    use schema::post_reports::dsl::*;
+
+    let resolved = diesel::update(
+        post_reports
+            .filter(post_id.eq(reported))
+            .filter(resolved_at.is_null()),
+    )
+    .set(resolved_at.eq(diesel::dsl::now))
+    .execute(conn)
+    .await?;
+    Ok(resolved)
+}
+
+async fn record(
+    conn: &mut AsyncPgConnection,
+    action: NewModerationAction<'_>,
+) -> anyhow::Result<()> {
+    use schema::moderation_actions::dsl::moderation_actions;
+
+    diesel::insert_into(moderation_actions)
+        .values(action)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::future::Future;
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::models::notification::*;
+use crate::schema;
+
+use super::{Pool, Svc};
+
+pub trait NotificationService<E = anyhow::Error>: Svc {
+    /// Users mentioned in `content` as `@email` or `@handle`.
+    fn resolve_mentions(&self, content: &str) -> impl Future<Output = Result<Vec<i32>, E>> + Send;
+    /// Stores the notifications, except the ones users would get for their
+    /// own actions and reactions they were already told about.
+    /// Returns what was stored.
+    fn notify(
+        &self,
+        new: Vec<NewNotification>,
+    ) -> impl Future<Output = Result<Vec<NotificationView>, E>> + Send;
+    fn list(
+        &self,
+        user: i32,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<NotificationView>, E>> + Send;
+    fn unread_count(&self, user: i32) -> impl Future<Output = Result<i64, E>> + Send;
+    /// Marks one, or with `None` every, unread notification of `user` as read.
+    fn mark_read(
+        &self,
+        user: i32,
+        notification: Option<Uuid>,
+    ) -> impl Future<Output = Result<usize, E>> + Send;
+}
+
+#[derive(Clone)]
+pub struct NotificationServiceDb {
+    db: Pool,
+}
+
+impl Svc for NotificationServiceDb {}
+
+impl NotificationService<anyhow::Error> for NotificationServiceDb {
+    async fn resolve_mentions(&self, content: &str) -> anyhow::Result<Vec<i32>> {
+        use schema::users::dsl::*;
+
+        let names = parse_mentions(content);
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut conn = self.db.get().await?;
+        let ids = users
+            .filter(email.eq_any(&names).or(handle.eq_any(&names)))
+            .select(id)
+            .load(&mut conn)
+            .await?;
+        Ok(ids)
+    }
+
+    async fn notify(&self, mut new: Vec<NewNotification>) -> anyhow::Result<Vec<NotificationView>> {
+        use schema::notifications::dsl::*;
+
+        // one notification per user and post is enough, e.g. a reply that
+        // also mentions the parent's author
+        let mut seen = HashSet::new();
+        new.retain(|n| n.user_id != n.actor_id && seen.insert((n.user_id, n.post_id)));
+        if new.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut conn = self.db.get().await?;
+        // `ux_notifications_reaction` turns away repeated reactions
+        let ids: Vec<Uuid> = diesel::insert_into(notifications)
+            .values(&new)
+            .on_conflict_do_nothing()
+            .returning(id)
+            .get_results(&mut conn)
+            .await?;
+        let views = notification_views()
+            .filter(id.eq_any(ids))
+            .order(created_at.desc())
+            .load(&mut conn)
+            .await?;
+        Ok(views)
+    }
+
+    async fn list(&self, user: i32, limit: i64) -> anyhow::Result<Vec<NotificationView>> {
+        use schema::notifications::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let views = notification_views()
+            .filter(user_id.eq(user))
+            .order(created_at.desc())
+            .limit(limit)
+            .load(&mut conn)
+            .await?;
+        Ok(views)
+    }
+
+    async fn unread_count(&self, user: i32) -> anyhow::Result<i64> {
+        use schema::notifications::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let n = notifications
+            .filter(user_id.eq(user))
+            .filter(read_at.is_null())
+            .count()
+            .get_result(&mut conn)
+            .await?;
+        Ok(n)
+    }
+
+    async fn mark_read(&self, user: i32, notification: Option<Uuid>) -> anyhow::Result<usize> {
+        use schema::notifications::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let mut query = diesel::update(notifications)
+            .filter(user_id.eq(user))
+            .filter(read_at.is_null())
+            .into_boxed();
+        if let Some(n) = notification {
+            query = query.filter(id.eq(n));
+        }
+        let n = query
+            .set(read_at.eq(diesel::dsl::now))
+            .execute(&mut conn)
+            .await?;
+        Ok(n)
+    }
+}
+
+impl NotificationServiceDb {
+    pub fn new(db: Pool) -> Self {
+        Self { db }
+    }
+}
+
+/// Notifications joined with what `NotificationView` needs of the actor.
+#[diesel::dsl::auto_type(no_type_alias)]
+fn notification_views() -> _ {
+    schema::notifications::table
+        .inner_join(schema::users::table.on(schema::users::id.eq(schema::notifications::actor_id)))
+        .select((
+            schema::notifications::id,
+            schema::notifications::user_id,
+            schema::notifications::kind,
+            schema::notifications::actor_id,
+            schema::users::email,
+            schema::notifications::post_id,
+            schema::notifications::created_at,
+            schema::notifications::read_at,
+        ))
+}
+
+/// The `@email` / `@handle` words in `content`, without the `@`, in order of
+/// first appearance.
+pub fn parse_mentions(content: &str) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for word in content.split_whitespace() {
+        let Some(name) = word
+            .trim_start_matches(['(', '[', '"', '\''])
+            .strip_prefix('@')
+            .map(|w| w.trim_end_matches(|c: char| !c.is_alphanumeric()))
+        else {
+            continue;
+        };
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_at_either_end_of_the_text() {
+        assert_eq!(parse_mentions("@alice hi"), ["alice"]);
+        assert_eq!(parse_mentions("hi @alice"), ["alice"]);
+        assert_eq!(parse_mentions("@alice"), ["alice"]);
+    }
+
+    #[test]
+    fn punctuation_is_not_part_of_the_name() {
+        assert_eq!(
+            parse_mentions("thanks @alice, @bob! (@carol) \"@dave\"?"),
+            ["alice", "bob", "carol", "dave"]
+        );
+        // except inside an email address
+        assert_eq!(parse_mentions("cc @alice@x.io."), ["alice@x.io"]);
+    }
+
+    #[test]
+    fn email_addresses_are_not_mentions() {
+        assert!(parse_mentions("write to alice@x.io or bob@x.io").is_empty());
+        assert!(parse_mentions("just an @ sign").is_empty());
+    }
+
+    // against a migrated database, e.g.
+    // `TEST_DATABASE_URL=postgresql://localhost/app cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn repeated_reactions_notify_once() {
+        use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+
+        use crate::models::post::{CreatePost, NewPost, PostStatus};
+        use crate::models::reaction::Reaction;
+        use crate::models::user::CreateUser;
+        use crate::services::posts::{PostService, PostServiceDb};
+        use crate::services::users::{UserService, UserServiceDb};
+
+        let url = std::env::var("TEST_DATABASE_URL").unwrap();
+        let db = Pool::builder(AsyncDieselConnectionManager::new(url))
+            .build()
+            .unwrap();
+        let (user_svc, post_svc) = (
+            UserServiceDb::new(db.clone()),
+            PostServiceDb::new(db.clone()),
+        );
+        let notify_svc = NotificationServiceDb::new(db.clone());
+
+        let mut users = vec![];
+        for _ in 0..2 {
+            let email = format!("{}@example.com", Uuid::now_v7());
+            let user = user_svc
+                .create_user(&CreateUser {
+                    email,
+                    handle: None,
+                })
+                .await
+                .unwrap();
+            users.push(user.id);
+        }
+        let (author, reactor) = (users[0], users[1]);
+        let post = CreatePost {
+            user_id: author,
+            post_content: "hi".to_owned(),
+            intent: Default::default(),
+            publish_at: None,
+        };
+        let (post, _) = post_svc
+            .create_post(&NewPost::new(&post, PostStatus::Published, None), &[])
+            .await
+            .unwrap();
+
+        let reaction = NewNotification {
+            user_id: author,
+            kind: NotificationKind::Reaction,
+            actor_id: reactor,
+            post_id: Some(post.id),
+        };
+        let mut stored = 0;
+        for _ in 0..3 {
+            let (given, _) = post_svc
+                .toggle_reaction(post.id, reactor, Reaction::Like)
+                .await
+                .unwrap()
+                .unwrap();
+            if given {
+                stored += notify_svc
+                    .notify(vec![reaction.clone()])
+                    .await
+                    .unwrap()
+                    .len();
+            }
+        }
+        assert_eq!(stored, 1);
+        assert_eq!(notify_svc.list(author, 10).await.unwrap().len(), 1);
+
+        let mut conn = db.get().await.unwrap();
+        diesel::delete(schema::posts::table.find(post.id))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        diesel::delete(schema::users::table.filter(schema::users::id.eq_any(users)))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn duplicate_mentions_count_once() {
+        assert_eq!(parse_mentions("@bob @alice @bob, @alice"), ["bob", "alice"]);
+    }
+}
@@ -0,0 +1,789 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use diesel_full_text_search::{RegConfig, TsQuery, TsVectorExtensions, ts_rank_cd};
+use uuid::Uuid;
+
+use crate::models::attachment::{Attachment, NewAttachment, StoredImage};
+use crate::models::post::*;
+use crate::models::reaction::{NewPostReaction, Reaction, ReactionCounts};
+use crate::models::revision::{NewPostRevision, PostEdit, PostRevision};
+use crate::models::{Page, Sort};
+use crate::schema;
+use crate::templating::markdown;
+
+use super::{Pool, Svc};
+
+// must match the configuration of the generated `posts.post_content_tsv` column
+const SEARCH_CONFIG: &str = "'english'::regconfig";
+
+// the posts an author can still change before they go out; held ones are up
+// to the moderators
+const DRAFT_STATUSES: [PostStatus; 2] = [PostStatus::Draft, PostStatus::Scheduled];
+
+const HEADLINE_START: &str = "[[mark]]";
+const HEADLINE_STOP: &str = "[[endmark]]";
+
+diesel::define_sql_function! {
+    #[sql_name = "websearch_to_tsquery"]
+    fn websearch_to_tsquery(config: RegConfig, querytext: Text) -> TsQuery;
+}
+
+diesel::define_sql_function! {
+    #[sql_name = "ts_headline"]
+    fn ts_headline(config: RegConfig, document: Text, query: TsQuery, options: Text) -> Text;
+}
+
+pub trait PostService<E = anyhow::Error>: Svc {
+    /// Stores `post` together with the attachments for `images`.
+    fn create_post(
+        &self,
+        post: &NewPost<'_>,
+        images: &[StoredImage],
+    ) -> impl Future<Output = Result<(Post, Vec<Attachment>), E>> + Send;
+    fn get_post(&self, post_id: Uuid) -> impl Future<Output = Result<Option<Post>, E>> + Send;
+    /// Every reply in the thread rooted at `root`, oldest first. The root
+    /// itself is not included.
+    fn get_thread(&self, root: Uuid) -> impl Future<Output = Result<Vec<Post>, E>> + Send;
+    /// A reply with a `held_reason` waits for a moderator and only counts
+    /// towards the replies of `parent` once approved.
+    fn create_reply(
+        &self,
+        parent: Uuid,
+        reply: &CreateReply,
+        held_reason: Option<String>,
+    ) -> impl Future<Output = Result<Option<Post>, E>> + Send;
+    fn reaction_counts(
+        &self,
+        post_ids: &[Uuid],
+    ) -> impl Future<Output = Result<HashMap<Uuid, ReactionCounts>, E>> + Send;
+    fn attachments(
+        &self,
+        post_ids: &[Uuid],
+    ) -> impl Future<Output = Result<HashMap<Uuid, Vec<Attachment>>, E>> + Send;
+    /// Gives `reaction` if the user had not given it to the post yet, takes it
+    /// back otherwise. Returns whether it was given and the post's new
+    /// counts, `None` when the post does not exist.
+    fn toggle_reaction(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        reaction: Reaction,
+    ) -> impl Future<Output = Result<Option<(bool, ReactionCounts)>, E>> + Send;
+    /// Top-level posts by everyone `user` follows, newest first, starting
+    /// after `cursor`.
+    fn home_feed(
+        &self,
+        user: i32,
+        cursor: Option<FeedCursor>,
+        limit: i64,
+    ) -> impl Future<Output = Result<Page<PostCard>, E>> + Send;
+    fn search(
+        &self,
+        search: &SearchPosts,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<PostSearchHit>, E>> + Send;
+    /// The drafts and scheduled posts of `author`, last edited first.
+    fn drafts(&self, author: i32) -> impl Future<Output = Result<Vec<Post>, E>> + Send;
+    fn get_draft(
+        &self,
+        author: i32,
+        post_id: Uuid,
+    ) -> impl Future<Output = Result<Option<Post>, E>> + Send;
+    /// Rewrites an unpublished post of `author`, possibly publishing it.
+    /// `None` when there is no such post.
+    fn update_draft(
+        &self,
+        post_id: Uuid,
+        draft: &NewPost<'_>,
+    ) -> impl Future<Output = Result<Option<Post>, E>> + Send;
+    /// Publishes up to `limit` scheduled posts whose time has come. Safe to
+    /// call from several replicas at once: each post is claimed by only one.
+    fn publish_due(&self, limit: i64) -> impl Future<Output = Result<Vec<Post>, E>> + Send;
+    /// How many posts `author` started since `since`, drafts and `except`
+    /// aside, or with `content` how many of them say exactly that.
+    fn count_recent(
+        &self,
+        author: i32,
+        since: DateTime<Utc>,
+        content: Option<&str>,
+        except: Option<Uuid>,
+    ) -> impl Future<Output = Result<i64, E>> + Send;
+    /// Changes a published post of `edit.edited_by`, keeping what it replaces
+    /// as a revision. `None` when there is no such post.
+    fn edit_post(
+        &self,
+        post_id: Uuid,
+        edit: &PostEdit<'_>,
+    ) -> impl Future<Output = Result<Option<Post>, E>> + Send;
+    /// The earlier versions of a post, oldest first.
+    fn revisions(&self, post_id: Uuid)
+    -> impl Future<Output = Result<Vec<PostRevision>, E>> + Send;
+    /// Puts an earlier version of a post back on behalf of `moderator`. The
+    /// version it replaces becomes a revision itself, nothing is lost.
+    /// `None` when there is no such revision of the post.
+    fn restore_revision(
+        &self,
+        post_id: Uuid,
+        revision_id: Uuid,
+        moderator: i32,
+    ) -> impl Future<Output = Result<Option<Post>, E>> + Send;
+}
+
+#[derive(Clone)]
+pub struct PostServiceDb {
+    db: Pool,
+}
+
+impl Svc for PostServiceDb {}
+
+impl PostService<anyhow::Error> for PostServiceDb {
+    async fn create_post(
+        &self,
+        post: &NewPost<'_>,
+        images: &[StoredImage],
+    ) -> anyhow::Result<(Post, Vec<Attachment>)> {
+        use schema::post_attachments::dsl::post_attachments;
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                let created = diesel::insert_into(posts)
+                    .values(post)
+                    .returning(Post::as_returning())
+                    .get_result(conn)
+                    .await?;
+
+                let new: Vec<_> = images
+                    .iter()
+                    .map(|image| NewAttachment::new(created.id, image))
+                    .collect();
+                let attachments = diesel::insert_into(post_attachments)
+                    .values(&new)
+                    .returning(Attachment::as_returning())
+                    .get_results(conn)
+                    .await?;
+                Ok((created, attachments))
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn get_post(&self, post_id: Uuid) -> anyhow::Result<Option<Post>> {
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let post = posts
+            .find(post_id)
+            .filter(status.eq(PostStatus::Published))
+            .filter(hidden_at.is_null())
+            .select(Post::as_select())
+            .first(&mut conn)
+            .await
+            .optional()?;
+        Ok(post)
+    }
+
+    async fn get_thread(&self, root: Uuid) -> anyhow::Result<Vec<Post>> {
+        let mut conn = self.db.get().await?;
+        let replies = thread_query(root)
+            .select(Post::as_select())
+            .load(&mut conn)
+            .await?;
+        Ok(replies)
+    }
+
+    /// Returns `None` when `parent` does not exist.
+    async fn create_reply(
+        &self,
+        parent: Uuid,
+        reply: &CreateReply,
+        held: Option<String>,
+    ) -> anyhow::Result<Option<Post>> {
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                // bumping the counter first also locks the parent row
+                let parent_post = posts
+                    .find(parent)
+                    .filter(status.eq(PostStatus::Published))
+                    .filter(hidden_at.is_null());
+                let bump = i32::from(held.is_none());
+                let Some(parent_root) = diesel::update(parent_post)
+                    .set(reply_count.eq(reply_count + bump))
+                    .returning(root_id)
+                    .get_result::<Option<Uuid>>(conn)
+                    .await
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+
+                let post = diesel::insert_into(posts)
+                    .values(NewReply {
+                        user_id: reply.user_id,
+                        post_content: &reply.post_content,
+                        post_content_html: markdown::render(&reply.post_content),
+                        parent_id: parent,
+                        root_id: parent_root.unwrap_or(parent),
+                        status: if held.is_none() {
+                            PostStatus::Published
+                        } else {
+                            PostStatus::Held
+                        },
+                        held_reason: held,
+                    })
+                    .returning(Post::as_returning())
+                    .get_result(conn)
+                    .await?;
+                Ok(Some(post))
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn reaction_counts(
+        &self,
+        post_ids: &[Uuid],
+    ) -> anyhow::Result<HashMap<Uuid, ReactionCounts>> {
+        let mut conn = self.db.get().await?;
+        load_reaction_counts(&mut conn, post_ids).await
+    }
+
+    async fn attachments(
+        &self,
+        post_ids: &[Uuid],
+    ) -> anyhow::Result<HashMap<Uuid, Vec<Attachment>>> {
+        let mut conn = self.db.get().await?;
+        load_attachments(&mut conn, post_ids).await
+    }
+
+    async fn toggle_reaction(
+        &self,
+        post_id: Uuid,
+        user_id: i32,
+        reaction: Reaction,
+    ) -> anyhow::Result<Option<(bool, ReactionCounts)>> {
+        use schema::post_reactions::dsl as pr;
+        use schema::posts::dsl as p;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                let exists = p::posts
+                    .find(post_id)
+                    .filter(p::status.eq(PostStatus::Published))
+                    .filter(p::hidden_at.is_null())
+                    .select(p::id)
+                    .first::<Uuid>(conn)
+                    .await
+                    .optional()?;
+                if exists.is_none() {
+                    return Ok(None);
+                }
+
+                let given = diesel::insert_into(pr::post_reactions)
+                    .values(NewPostReaction {
+                        post_id,
+                        user_id,
+                        reaction,
+                    })
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+                if given == 0 {
+                    diesel::delete(pr::post_reactions.find((post_id, user_id, reaction)))
+                        .execute(conn)
+                        .await?;
+                }
+
+                let mut counts = load_reaction_counts(conn, &[post_id]).await?;
+                Ok(Some((
+                    given > 0,
+                    counts.remove(&post_id).unwrap_or_default(),
+                )))
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn home_feed(
+        &self,
+        user: i32,
+        cursor: Option<FeedCursor>,
+        limit: i64,
+    ) -> anyhow::Result<Page<PostCard>> {
+        use schema::follows::dsl as f;
+        use schema::posts::dsl::*;
+
+        let followed = f::follows
+            .filter(f::follower_id.eq(user))
+            .select(f::followee_id);
+        let mut query = posts
+            .filter(user_id.eq_any(followed))
+            .filter(parent_id.is_null())
+            .filter(status.eq(PostStatus::Published))
+            .filter(hidden_at.is_null())
+            .into_boxed();
+        if let Some(c) = cursor {
+            query = query.filter(
+                created_at
+                    .lt(c.created_at)
+                    .or(created_at.eq(c.created_at).and(id.lt(c.id))),
+            );
+        }
+
+        let mut conn = self.db.get().await?;
+        // one extra row tells whether there is a next page
+        let mut page: Vec<Post> = query
+            .order((created_at.desc(), id.desc()))
+            .limit(limit + 1)
+            .select(Post::as_select())
+            .load(&mut conn)
+            .await?;
+        let next_cursor = if page.len() as i64 > limit {
+            page.truncate(limit as usize);
+            page.last().map(|p| FeedCursor::from(p).to_string())
+        } else {
+            None
+        };
+
+        let ids: Vec<Uuid> = page.iter().map(|p| p.id).collect();
+        let mut reactions = load_reaction_counts(&mut conn, &ids).await?;
+        let mut attachments = load_attachments(&mut conn, &ids).await?;
+        let items = page
+            .into_iter()
+            .map(|post| PostCard {
+                reactions: reactions.remove(&post.id).unwrap_or_default(),
+                attachments: attachments.remove(&post.id).unwrap_or_default(),
+                post,
+            })
+            .collect();
+
+        Ok(Page { items, next_cursor })
+    }
+
+    async fn search(&self, search: &SearchPosts, limit: i64) -> anyhow::Result<Vec<PostSearchHit>> {
+        use schema::posts::dsl::*;
+
+        if search.q.trim().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let tsquery = || websearch_to_tsquery(sql::<RegConfig>(SEARCH_CONFIG), search.q.as_str());
+        let rank = || ts_rank_cd(post_content_tsv, tsquery());
+        let headline = ts_headline(
+            sql::<RegConfig>(SEARCH_CONFIG),
+            post_content,
+            tsquery(),
+            format!(
+                r#"StartSel="{HEADLINE_START}", StopSel="{HEADLINE_STOP}", MaxWords=35, MinWords=15"#
+            ),
+        );
+
+        let mut query = posts
+            .filter(post_content_tsv.matches(tsquery()))
+            .filter(status.eq(PostStatus::Published))
+            .filter(hidden_at.is_null())
+            .into_boxed();
+        let search_tags = search.tags();
+        if !search_tags.is_empty() {
+            query = query.filter(tags.contains(search_tags));
+        }
+        if let Some(author) = search.author {
+            query = query.filter(user_id.eq(author));
+        }
+        if let Some(since) = search.since {
+            query = query.filter(created_at.ge(since));
+        }
+        if let Some(until) = search.until {
+            query = query.filter(created_at.lt(until));
+        }
+        query = match search.sort {
+            None => query.order((rank().desc(), id.desc())),
+            Some(Sort::Newest) => query.order((created_at.desc(), id.desc())),
+            Some(Sort::Oldest) => query.order((created_at.asc(), id.asc())),
+        };
+
+        let mut conn = self.db.get().await?;
+        let hits: Vec<(Post, f32, String)> = query
+            .select((Post::as_select(), rank(), headline))
+            .limit(limit)
+            .load(&mut conn)
+            .await?;
+
+        let ids: Vec<Uuid> = hits.iter().map(|(post, _, _)| post.id).collect();
+        let mut reactions = load_reaction_counts(&mut conn, &ids).await?;
+        let mut attachments = load_attachments(&mut conn, &ids).await?;
+
+        Ok(hits
+            .into_iter()
+            .map(|(post, rank, headline)| PostSearchHit {
+                reactions: reactions.remove(&post.id).unwrap_or_default(),
+                attachments: attachments.remove(&post.id).unwrap_or_default(),
+                post,
+                rank,
+                headline: escape_headline(&headline),
+            })
+            .collect())
+    }
+
+    async fn drafts(&self, author: i32) -> anyhow::Result<Vec<Post>> {
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let drafts = posts
+            .filter(user_id.eq(author))
+            .filter(status.eq_any(DRAFT_STATUSES))
+            .order((updated_at.desc(), id.desc()))
+            .select(Post::as_select())
+            .load(&mut conn)
+            .await?;
+        Ok(drafts)
+    }
+
+    async fn get_draft(&self, author: i32, post_id: Uuid) -> anyhow::Result<Option<Post>> {
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let draft = posts
+            .find(post_id)
+            .filter(user_id.eq(author))
+            .filter(status.eq_any(DRAFT_STATUSES))
+            .select(Post::as_select())
+            .first(&mut conn)
+            .await
+            .optional()?;
+        Ok(draft)
+    }
+
+    async fn update_draft(
+        &self,
+        post_id: Uuid,
+        draft: &NewPost<'_>,
+    ) -> anyhow::Result<Option<Post>> {
+        use schema::posts::dsl::*;
+
+        let target = posts
+            .find(post_id)
+            .filter(user_id.eq(draft.user_id))
+            .filter(status.eq_any(DRAFT_STATUSES));
+        let changes = (
+            post_content.eq(draft.post_content),
+            post_content_html.eq(&draft.post_content_html),
+            status.eq(draft.status),
+            publish_at.eq(draft.publish_at),
+            held_reason.eq(&draft.held_reason),
+        );
+
+        let mut conn = self.db.get().await?;
+        let updated = if draft.status == PostStatus::Published {
+            // a post is new to everyone when it goes out, not when it was started
+            diesel::update(target)
+                .set((changes, created_at.eq(diesel::dsl::now)))
+                .returning(Post::as_returning())
+                .get_result(&mut conn)
+                .await
+        } else {
+            diesel::update(target)
+                .set(changes)
+                .returning(Post::as_returning())
+                .get_result(&mut conn)
+                .await
+        };
+        Ok(updated.optional()?)
+    }
+
+    async fn publish_due(&self, limit: i64) -> anyhow::Result<Vec<Post>> {
+        use schema::posts::dsl::*;
+        use schema::users::dsl as u;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                // held back until the suspension of the author is over
+                let suspended = u::users
+                    .filter(u::suspended_until.gt(diesel::dsl::now))
+                    .select(u::id);
+                // rows another replica is publishing right now are skipped, not waited for
+                let due: Vec<Uuid> = posts
+                    .filter(status.eq(PostStatus::Scheduled))
+                    .filter(publish_at.le(diesel::dsl::now))
+                    .filter(user_id.ne_all(suspended))
+                    .order(publish_at.asc())
+                    .limit(limit)
+                    .select(id)
+                    .for_update()
+                    .skip_locked()
+                    .load(conn)
+                    .await?;
+                if due.is_empty() {
+                    return Ok(vec![]);
+                }
+
+                let published = diesel::update(posts.filter(id.eq_any(&due)))
+                    .set((
+                        status.eq(PostStatus::Published),
+                        created_at.eq(diesel::dsl::now),
+                    ))
+                    .returning(Post::as_returning())
+                    .get_results(conn)
+                    .await?;
+                Ok(published)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn count_recent(
+        &self,
+        author: i32,
+        since: DateTime<Utc>,
+        content: Option<&str>,
+        except: Option<Uuid>,
+    ) -> anyhow::Result<i64> {
+        use schema::posts::dsl::*;
+
+        let mut query = posts
+            .filter(user_id.eq(author))
+            .filter(created_at.ge(since))
+            .filter(status.ne(PostStatus::Draft))
+            .into_boxed();
+        if let Some(content) = content {
+            query = query.filter(post_content.eq(content));
+        }
+        if let Some(except) = except {
+            query = query.filter(id.ne(except));
+        }
+
+        let mut conn = self.db.get().await?;
+        Ok(query.count().get_result(&mut conn).await?)
+    }
+
+    async fn edit_post(&self, post_id: Uuid, edit: &PostEdit<'_>) -> anyhow::Result<Option<Post>> {
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                let Some(post) = posts
+                    .find(post_id)
+                    .filter(user_id.eq(edit.edited_by))
+                    .filter(status.eq(PostStatus::Published))
+                    .filter(hidden_at.is_null())
+                    .select(Post::as_select())
+                    .for_update()
+                    .first(conn)
+                    .await
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+                revise_post(conn, post, edit).await.map(Some)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn revisions(&self, revised: Uuid) -> anyhow::Result<Vec<PostRevision>> {
+        use schema::post_revisions::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let revisions = post_revisions
+            .filter(post_id.eq(revised))
+            .order((created_at.asc(), id.asc()))
+            .select(PostRevision::as_select())
+            .load(&mut conn)
+            .await?;
+        Ok(revisions)
+    }
+
+    async fn restore_revision(
+        &self,
+        post_id: Uuid,
+        revision_id: Uuid,
+        moderator: i32,
+    ) -> anyhow::Result<Option<Post>> {
+        use schema::post_revisions::dsl as r;
+        use schema::posts::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        conn.transaction(|conn| {
+            async move {
+                let Some(post) = posts
+                    .find(post_id)
+                    .filter(status.eq(PostStatus::Published))
+                    .select(Post::as_select())
+                    .for_update()
+                    .first(conn)
+                    .await
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+                let Some(revision) = r::post_revisions
+                    .find(revision_id)
+                    .filter(r::post_id.eq(post_id))
+                    .select(PostRevision::as_select())
+                    .first(conn)
+                    .await
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+
+                let edit = PostEdit::new(moderator, &revision.post_content, revision.tags.clone());
+                revise_post(conn, post, &edit).await.map(Some)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+impl PostServiceDb {
+    pub fn new(db: Pool) -> Self {
+        Self { db }
+    }
+}
+
+/// Keeps `post` as it is as a revision and applies `edit` to it. Run inside
+/// a transaction holding a lock on the post. An edit changing nothing
+/// leaves no revision.
+async fn revise_post(
+    conn: &mut AsyncPgConnection,
+    post: Post,
+    edit: &PostEdit<'_>,
+) -> anyhow::Result<Post> {
+    use schema::post_revisions::dsl::post_revisions;
+    use schema::posts::dsl::*;
+
+    if post.post_content == edit.post_content && post.tags == edit.tags {
+        return Ok(post);
+    }
+
+    diesel::insert_into(post_revisions)
+        .values(NewPostRevision {
+            post_id: post.id,
+            post_content: &post.post_content,
+            tags: &post.tags,
+            edited_by: edit.edited_by,
+        })
+        .execute(conn)
+        .await?;
+    let revised = diesel::update(posts.find(post.id))
+        .set((
+            post_content.eq(edit.post_content),
+            post_content_html.eq(&edit.post_content_html),
+            tags.eq(&edit.tags),
+            edited_at.eq(diesel::dsl::now),
+        ))
+        .returning(Post::as_returning())
+        .get_result(conn)
+        .await?;
+    let Some(reason) = &edit.held_reason else {
+        return Ok(revised);
+    };
+
+    // out of the thread until a moderator approves it again
+    if let Some(parent) = revised.parent_id {
+        diesel::update(posts.find(parent))
+            .set(reply_count.eq(reply_count - 1))
+            .execute(conn)
+            .await?;
+    }
+    let held = diesel::update(posts.find(post.id))
+        .set((status.eq(PostStatus::Held), held_reason.eq(reason)))
+        .returning(Post::as_returning())
+        .get_result(conn)
+        .await?;
+    Ok(held)
+}
+
+async fn load_reaction_counts(
+    conn: &mut AsyncPgConnection,
+    post_ids: &[Uuid],
+) -> anyhow::Result<HashMap<Uuid, ReactionCounts>> {
+    use schema::post_reactions::dsl::*;
+
+    let rows: Vec<(Uuid, Reaction, i64)> = post_reactions
+        .filter(post_id.eq_any(post_ids))
+        .group_by((post_id, reaction))
+        .select((post_id, reaction, diesel::dsl::count_star()))
+        .load(conn)
+        .await?;
+
+    let mut counts: HashMap<Uuid, ReactionCounts> = HashMap::new();
+    for (post, r, n) in rows {
+        counts.entry(post).or_default().insert(r, n);
+    }
+    Ok(counts)
+}
+
+async fn load_attachments(
+    conn: &mut AsyncPgConnection,
+    post_ids: &[Uuid],
+) -> anyhow::Result<HashMap<Uuid, Vec<Attachment>>> {
+    use schema::post_attachments::dsl::*;
+
+    let rows: Vec<Attachment> = post_attachments
+        .filter(post_id.eq_any(post_ids))
+        .order((post_id, id))
+        .select(Attachment::as_select())
+        .load(conn)
+        .await?;
+
+    let mut by_post: HashMap<Uuid, Vec<Attachment>> = HashMap::new();
+    for a in rows {
+        by_post.entry(a.post_id).or_default().push(a);
+    }
+    Ok(by_post)
+}
+
+/// `ts_headline` does not escape the document, so the highlight markers are
+/// plain text until everything else has been escaped.
+/// The posts under `root` anyone may see, oldest first. Held and draft
+/// replies wait for a moderator or their author.
+fn thread_query(root: Uuid) -> schema::posts::BoxedQuery<'static, Pg> {
+    use schema::posts::dsl::*;
+
+    posts
+        .filter(root_id.eq(root))
+        .filter(status.eq(PostStatus::Published))
+        .filter(hidden_at.is_null())
+        .order((created_at.asc(), id.asc()))
+        .into_boxed()
+}
+
+fn escape_headline(headline: &str) -> String {
+    tera::escape_html(headline)
+        .replace(HEADLINE_START, "<mark>")
+        .replace(HEADLINE_STOP, "</mark>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threads_leave_out_unpublished_and_hidden_posts() {
+        let query = diesel::debug_query::<Pg, _>(&thread_query(Uuid::nil())).to_string();
+        assert!(query.contains(r#"("posts"."status" = $2)) AND ("posts"."hidden_at" IS NULL)"#));
+        assert!(query.ends_with("-- binds: [00000000-0000-0000-0000-000000000000, Published]"));
+    }
+}
@@ -2,6 +2,8 @@ use std::future::Future;
 
 use diesel::prelude::*;
 
+use crate::models::Sort;
+use crate::models::post::FeedCursor;
 use crate::models::user::*;
 use diesel_async::RunQueryDsl;
 
@@ -10,8 +12,21 @@ use crate::schema;
 use super::{Pool, Svc};
 
 pub trait UserService<E = anyhow::Error>: Svc {
-    fn get_users(&self, offset: i32, limit: i64) -> impl Future<Output = Result<Vec<User>, E>> + Send;
+    /// The users of `list` in its order, from after `cursor`.
+    fn get_users(
+        &self,
+        list: &ListUsers,
+        cursor: Option<FeedCursor<i32>>,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<User>, E>> + Send;
     fn create_user(&self, user: &CreateUser) -> impl Future<Output = Result<User, E>> + Send;
+    fn get_user(&self, user_id: i32) -> impl Future<Output = Result<Option<User>, E>> + Send;
+    /// Returns `false` if the user does not exist.
+    fn set_avatar(
+        &self,
+        user_id: i32,
+        avatar_hash: &str,
+    ) -> impl Future<Output = Result<bool, E>> + Send;
 }
 
 #[derive(Clone)]
@@ -22,12 +37,41 @@ pub struct UserServiceDb {
 impl Svc for UserServiceDb {}
 
 impl UserService<anyhow::Error> for UserServiceDb {
-    async fn get_users(&self, offset: i32, limit: i64) -> anyhow::Result<Vec<User>> {
+    async fn get_users(
+        &self,
+        list: &ListUsers,
+        cursor: Option<FeedCursor<i32>>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<User>> {
         use schema::users::dsl::*;
 
+        let mut query = users.into_boxed();
+        if let Some(since) = list.since {
+            query = query.filter(created_at.ge(since));
+        }
+        if let Some(until) = list.until {
+            query = query.filter(created_at.lt(until));
+        }
+        query = match (list.sort, cursor) {
+            (Sort::Newest, Some(c)) => query.filter(
+                created_at
+                    .lt(c.created_at)
+                    .or(created_at.eq(c.created_at).and(id.lt(c.id))),
+            ),
+            (Sort::Oldest, Some(c)) => query.filter(
+                created_at
+                    .gt(c.created_at)
+                    .or(created_at.eq(c.created_at).and(id.gt(c.id))),
+            ),
+            (_, None) => query,
+        };
+        query = match list.sort {
+            Sort::Newest => query.order((created_at.desc(), id.desc())),
+            Sort::Oldest => query.order((created_at.asc(), id.asc())),
+        };
+
         let mut conn = self.db.get().await?;
-        let us: Vec<User> = users
-            .filter(id.gt(offset))
+        let us: Vec<User> = query
             .limit(limit)
             .select(User::as_select())
             .load(&mut conn)
@@ -42,11 +86,36 @@ impl UserService<anyhow::Error> for UserServiceDb {
 
         let user = diesel::insert_into(users)
             .values(u)
+            .returning(User::as_returning())
             .get_result::<User>(&mut conn)
             .await?;
 
         Ok(user)
     }
+
+    async fn get_user(&self, user_id: i32) -> anyhow::Result<Option<User>> {
+        use schema::users::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let user = users
+            .find(user_id)
+            .select(User::as_select())
+            .first(&mut conn)
+            .await
+            .optional()?;
+        Ok(user)
+    }
+
+    async fn set_avatar(&self, user_id: i32, hash: &str) -> anyhow::Result<bool> {
+        use schema::users::dsl::*;
+
+        let mut conn = self.db.get().await?;
+        let updated = diesel::update(users.find(user_id))
+            .set(avatar_hash.eq(hash))
+            .execute(&mut conn)
+            .await?;
+        Ok(updated > 0)
+    }
 }
 
 impl UserServiceDb {
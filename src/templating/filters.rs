@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use tera::{Filter, Result, Value, from_value, to_value};
+
+use super::markdown;
+use crate::i18n::Message;
+
+/// `{{ post.created_at | relative_time }}` renders an RFC 3339 timestamp as
+/// e.g. "5 minutes ago" or "in 2 days", in the locale of the request.
+pub fn relative_time(value: &Value, _args: &HashMap<String, Value>) -> Result<Value> {
+    let at: DateTime<Utc> = from_value(value.clone())
+        .map_err(|e| tera::Error::msg(format!("relative_time expects a timestamp: {e}")))?;
+    to_value(humanize(Utc::now().signed_duration_since(at))).map_err(tera::Error::from)
+}
+
+fn humanize(ago: chrono::TimeDelta) -> String {
+    let secs = ago.num_seconds();
+    if secs.abs() < 45 {
+        return Message::new("time-just-now").localize();
+    }
+
+    let (n, unit) = match secs.abs() {
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 60 * 60 * 24 => (s / (60 * 60), "hour"),
+        s if s < 60 * 60 * 24 * 30 => (s / (60 * 60 * 24), "day"),
+        s if s < 60 * 60 * 24 * 365 => (s / (60 * 60 * 24 * 30), "month"),
+        s => (s / (60 * 60 * 24 * 365), "year"),
+    };
+    let n = n.max(1);
+
+    let id = if secs < 0 { "time-in" } else { "time-ago" };
+    Message::new(id).arg("n", n).arg("unit", unit).localize()
+}
+
+/// `{{ post | post_html }}` is the body of a post as sanitized HTML: the one
+/// rendered when it was written, or rendered now for posts stored before
+/// bodies were. Its output is not escaped again.
+pub struct PostHtml;
+
+impl Filter for PostHtml {
+    fn filter(&self, value: &Value, _args: &HashMap<String, Value>) -> Result<Value> {
+        if let Some(html) = value.get("post_content_html").and_then(Value::as_str) {
+            return Ok(Value::String(html.to_owned()));
+        }
+        let source = value
+            .get("post_content")
+            .and_then(Value::as_str)
+            .ok_or_else(|| tera::Error::msg("post_html expects a post"))?;
+        Ok(Value::String(markdown::render(source)))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
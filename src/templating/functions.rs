@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use fluent_bundle::{FluentArgs, FluentValue};
+use tera::{Result, Value, to_value};
+
+use crate::i18n;
+use crate::middleware::csrf;
+use crate::models::reaction::Reaction;
+
+/// `reaction_kinds()` lists every reaction with its emoji, in display order,
+/// so templates can render a button even for reactions nobody gave yet.
+pub fn reaction_kinds(_args: &HashMap<String, Value>) -> Result<Value> {
+    let kinds: Vec<_> = Reaction::ALL
+        .iter()
+        .map(|r| serde_json::json!({"reaction": r, "emoji": r.emoji()}))
+        .collect();
+    to_value(kinds).map_err(tera::Error::from)
+}
+
+/// `csrf_token()` is the CSRF token of the request being rendered, for the
+/// `hx-headers` of `layouts/base.html`.
+pub fn csrf_token(_args: &HashMap<String, Value>) -> Result<Value> {
+    csrf::current_token()
+        .map(Value::String)
+        .ok_or_else(|| tera::Error::msg("csrf_token() is only available while handling a request"))
+}
+
+/// `t(key="post-replies", count=post.reply_count)` is the message `key` of
+/// `src/locales/` in the locale of the request. The other arguments go to
+/// the message, numbers as numbers so they pick plural forms.
+pub fn t(args: &HashMap<String, Value>) -> Result<Value> {
+    let key = args
+        .get("key")
+        .and_then(Value::as_str)
+        .ok_or_else(|| tera::Error::msg("t() needs a `key`"))?;
+
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args.iter().filter(|(name, _)| *name != "key") {
+        let value = match value {
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => FluentValue::from(n),
+                None => FluentValue::from(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => FluentValue::String(Cow::Borrowed(s.as_str())),
+            Value::Null => FluentValue::None,
+            other => FluentValue::String(Cow::Owned(other.to_string())),
+        };
+        fluent_args.set(name.as_str(), value);
+    }
+    Ok(Value::String(i18n::format(key, Some(&fluent_args))))
+}
+
+/// `locale()` is the locale of the request, e.g. for `<html lang>`.
+pub fn locale(_args: &HashMap<String, Value>) -> Result<Value> {
+    Ok(Value::String(i18n::current().to_string()))
+}
+
+/// `locales()` lists the translated locales as `{id, name}`, named in their
+/// own language.
+pub fn locales(_args: &HashMap<String, Value>) -> Result<Value> {
+    let locales: Vec<_> = i18n::available()
+        .into_iter()
+        .map(|(id, name)| serde_json::json!({"id": id.to_string(), "name": name}))
+        .collect();
+    to_value(locales).map_err(tera::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use tera::{Context, Tera};
+
+    use super::t;
+    use crate::i18n;
+
+    #[tokio::test]
+    async fn t_picks_the_plural_form() {
+        i18n::init().unwrap();
+        let mut tera = Tera::default();
+        tera.register_function("t", t);
+        tera.add_raw_template("replies", r#"{{ t(key="post-replies", count=n) }}"#)
+            .unwrap();
+        let render = |n: i64| {
+            let mut ctx = Context::new();
+            ctx.insert("n", &n);
+            tera.render("replies", &ctx).unwrap()
+        };
+
+        assert_eq!(render(1), "1 reply");
+        assert_eq!(render(2), "2 replies");
+        let de = i18n::scope("de".parse().unwrap(), async { (render(1), render(0)) }).await;
+        assert_eq!(de, ("1 Antwort".to_owned(), "0 Antworten".to_owned()));
+    }
+}
@@ -0,0 +1,176 @@
+//! Post bodies are written in a CommonMark subset and rendered to HTML once,
+//! when the post is stored. Everything coming out of here has been through
+//! the sanitizer and is safe to put into a page as is.
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use linkify::{LinkFinder, LinkKind};
+use pulldown_cmark::{CowStr, Event, LinkType, Options, Parser, Tag, TagEnd};
+
+// the only markup a post can end up with, whatever its source says
+const ALLOWED_TAGS: [&str; 14] = [
+    "p",
+    "br",
+    "hr",
+    "em",
+    "strong",
+    "del",
+    "code",
+    "pre",
+    "blockquote",
+    "ul",
+    "ol",
+    "li",
+    "a",
+    "img",
+];
+
+static SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    // `empty` only clears the tags, the default attributes and schemes go too
+    let mut b = ammonia::Builder::empty();
+    b.add_tags(ALLOWED_TAGS)
+        .generic_attributes(HashSet::new())
+        .tag_attributes(HashMap::new())
+        .add_tag_attributes("a", ["href"])
+        .add_tag_attributes("ol", ["start"])
+        .add_tag_attributes("img", ["src", "alt", "title"])
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some("nofollow noopener noreferrer"))
+        .clean_content_tags(HashSet::from(["script", "style"]));
+    b
+});
+
+/// Renders the markdown `source` of a post to sanitized HTML. Raw HTML in
+/// the source is shown as text, bare URLs become links.
+pub fn render(source: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+
+    let mut html = String::with_capacity(source.len() * 3 / 2);
+    pulldown_cmark::html::push_html(&mut html, autolink(Parser::new_ext(source, options)));
+    SANITIZER.clean(&html).to_string()
+}
+
+/// Turns raw HTML into plain text and wraps URLs found in text outside of
+/// links and code in `<a>` tags.
+fn autolink<'a>(events: impl Iterator<Item = Event<'a>>) -> impl Iterator<Item = Event<'a>> {
+    let mut finder = LinkFinder::new();
+    finder.kinds(&[LinkKind::Url]);
+
+    let mut in_link_or_code = 0usize;
+    events.flat_map(move |event| match event {
+        Event::Start(tag @ (Tag::Link { .. } | Tag::CodeBlock(_))) => {
+            in_link_or_code += 1;
+            vec![Event::Start(tag)]
+        }
+        Event::End(tag @ (TagEnd::Link | TagEnd::CodeBlock)) => {
+            in_link_or_code = in_link_or_code.saturating_sub(1);
+            vec![Event::End(tag)]
+        }
+        Event::Html(raw) | Event::InlineHtml(raw) => vec![Event::Text(raw)],
+        Event::Text(text) if in_link_or_code == 0 => {
+            let mut out = Vec::new();
+            for span in finder.spans(&text) {
+                let s = CowStr::from(span.as_str().to_owned());
+                if span.kind().is_some() {
+                    out.push(Event::Start(Tag::Link {
+                        link_type: LinkType::Autolink,
+                        dest_url: s.clone(),
+                        title: CowStr::Borrowed(""),
+                        id: CowStr::Borrowed(""),
+                    }));
+                    out.push(Event::Text(s));
+                    out.push(Event::End(TagEnd::Link));
+                } else {
+                    out.push(Event::Text(s));
+                }
+            }
+            out
+        }
+        e => vec![e],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_html_becomes_text() {
+        assert_eq!(
+            render("<script>alert(1)</script>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(
+            render("a <b onclick=\"x()\">bold</b> move"),
+            "<p>a &lt;b onclick=\"x()\"&gt;bold&lt;/b&gt; move</p>\n"
+        );
+    }
+
+    #[test]
+    fn links_keep_safe_schemes_only() {
+        assert_eq!(
+            render("[x](javascript:alert(1))"),
+            "<p><a rel=\"nofollow noopener noreferrer\">x</a></p>\n"
+        );
+        assert_eq!(
+            render("[x](https://a.example \"title\") [y](ftp://a.example)"),
+            "<p><a href=\"https://a.example\" rel=\"nofollow noopener noreferrer\">x</a> \
+             <a rel=\"nofollow noopener noreferrer\">y</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn links_lose_their_extra_attributes() {
+        let a = "<a href=\"https://a.example\" onclick=\"x()\" style=\"y\" title=\"t\">x</a>";
+        // in a post the whole tag is text
+        assert_eq!(
+            render(a),
+            "<p>&lt;a href=\"https://a.example\" onclick=\"x()\" style=\"y\" \
+             title=\"t\"&gt;x&lt;/a&gt;</p>\n"
+        );
+        assert_eq!(
+            SANITIZER.clean(a).to_string(),
+            "<a href=\"https://a.example\" rel=\"nofollow noopener noreferrer\">x</a>"
+        );
+    }
+
+    #[test]
+    fn images_keep_src_alt_and_title() {
+        assert_eq!(
+            render("![a cat](https://a.example/i.png \"Tom\")"),
+            "<p><img src=\"https://a.example/i.png\" alt=\"a cat\" title=\"Tom\"></p>\n"
+        );
+        assert_eq!(
+            render("![x](javascript:alert(1))"),
+            "<p><img alt=\"x\"></p>\n"
+        );
+        assert_eq!(
+            SANITIZER
+                .clean("<img src=\"https://a.example/i.png\" onerror=\"x()\" style=\"y\">")
+                .to_string(),
+            "<img src=\"https://a.example/i.png\">"
+        );
+    }
+
+    #[test]
+    fn bare_urls_become_links_outside_links_and_code() {
+        let rel = "rel=\"nofollow noopener noreferrer\"";
+        assert_eq!(
+            render("see https://a.example now"),
+            format!("<p>see <a href=\"https://a.example\" {rel}>https://a.example</a> now</p>\n")
+        );
+        assert_eq!(
+            render("[https://a.example](https://b.example)"),
+            format!("<p><a href=\"https://b.example\" {rel}>https://a.example</a></p>\n")
+        );
+        assert_eq!(
+            render("`https://a.example`"),
+            "<p><code>https://a.example</code></p>\n"
+        );
+        assert_eq!(
+            render("    https://a.example"),
+            "<pre><code>https://a.example</code></pre>\n"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Rust side of the Tera templates in `src/templates/`: custom filters and
+//! functions registered on every `Tera` instance the app creates. Text in
+//! the templates comes from `t()`, see `i18n`.
+//!
+//! In development the templates are read from the source tree, so that
+//! `main` can reload them when they change. In production they are compiled
+//! into the binary and it runs from any directory.
+use macros::Template;
+use rust_embed::RustEmbed;
+use serde::Serialize;
+use tera::Tera;
+
+use crate::config::Env;
+
+mod filters;
+mod functions;
+pub mod markdown;
+
+pub const TEMPLATES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/templates");
+
+#[derive(RustEmbed)]
+#[folder = "src/templates/"]
+struct EmbeddedTemplates;
+
+/// Every template of `src/templates/`, named by their path below it.
+pub fn load(env: &Env) -> anyhow::Result<Tera> {
+    let mut tera = match env {
+        Env::Development => Tera::new(&format!("{TEMPLATES_DIR}/**/*"))?,
+        Env::Production => {
+            let templates = EmbeddedTemplates::iter()
+                .filter_map(|name| {
+                    let file = EmbeddedTemplates::get(&name)?;
+                    Some(String::from_utf8(file.data.into_owned()).map(|src| (name, src)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut tera = Tera::default();
+            tera.add_raw_templates(templates)?;
+            tera
+        }
+    };
+    register(&mut tera);
+    Ok(tera)
+}
+
+/// Renders `view` with its template, see `views`.
+pub fn render<V: Template + Serialize>(tera: &Tera, view: &V) -> tera::Result<String> {
+    tera.render(V::PATH, &tera::Context::from_serialize(view)?)
+}
+
+fn register(tera: &mut Tera) {
+    tera.register_filter("relative_time", filters::relative_time);
+    tera.register_filter("post_html", filters::PostHtml);
+    tera.register_function("reaction_kinds", functions::reaction_kinds);
+    tera.register_function("csrf_token", functions::csrf_token);
+    tera.register_function("t", functions::t);
+    tera.register_function("locale", functions::locale);
+    tera.register_function("locales", functions::locales);
+}
@@ -0,0 +1,47 @@
+//! The contexts of the Tera templates, one struct per template. They borrow
+//! what the handlers already have and are rendered with
+//! `templating::render`, or `HxRequest::render` for fragments that can also
+//! be opened as a page.
+//!
+//! `#[derive(Template)]` ties each struct to its template and tests that the
+//! template uses no variable the struct lacks, see `macros::Template`.
+use macros::Template;
+use serde::Serialize;
+
+pub mod moderation;
+pub mod notifications;
+pub mod posts;
+pub mod users;
+
+/// The start page. Everything else is swapped into it.
+#[derive(Serialize, Template)]
+#[template(path = "index.html")]
+pub struct Index {}
+
+/// A fragment opened as a page of its own, see `HxRequest::render`.
+#[derive(Serialize, Template)]
+#[template(path = "layouts/page.html")]
+pub struct Page<'a> {
+    pub title: &'a str,
+    pub content: &'a str,
+}
+
+/// The error page of a navigation.
+#[derive(Serialize, Template)]
+#[template(path = "error.html")]
+pub struct ErrorPage<'a> {
+    pub status: u16,
+    pub title: &'a str,
+    pub message: &'a str,
+    pub correlation_id: &'a str,
+}
+
+/// The error of an htmx request, swapped into `#errors`.
+#[derive(Serialize, Template)]
+#[template(path = "error_fragment.html")]
+pub struct ErrorFragment<'a> {
+    pub status: u16,
+    pub title: &'a str,
+    pub message: &'a str,
+    pub correlation_id: &'a str,
+}
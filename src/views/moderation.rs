@@ -0,0 +1,13 @@
+use macros::Template;
+use serde::Serialize;
+
+use crate::models::moderation::{ModerationAction, ReportedPost};
+use crate::models::post::Post;
+
+#[derive(Serialize, Template)]
+#[template(path = "moderation/queue.html")]
+pub struct Queue<'a> {
+    pub reported: &'a [ReportedPost],
+    pub held: &'a [Post],
+    pub actions: &'a [ModerationAction],
+}
@@ -0,0 +1,27 @@
+use macros::Template;
+use serde::Serialize;
+
+use crate::models::notification::NotificationView;
+
+/// The unread badge.
+#[derive(Serialize, Template)]
+#[template(path = "notifications/count.html")]
+pub struct Count {
+    pub unread: i64,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "notifications/list.html")]
+pub struct List<'a> {
+    pub user_id: i32,
+    pub unread: i64,
+    pub notifications: &'a [NotificationView],
+}
+
+/// A notification pushed over `/posts/ws`, with the new unread badge.
+#[derive(Serialize, Template)]
+#[template(path = "notifications/ws_notification.html")]
+pub struct WsNotification<'a> {
+    pub notification: &'a NotificationView,
+    pub unread: i64,
+}
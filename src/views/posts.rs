@@ -0,0 +1,129 @@
+use macros::Template;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::Page;
+use crate::models::attachment::Attachment;
+use crate::models::post::{Post, PostCard, PostSearchHit, PostThreadNode};
+use crate::models::reaction::ReactionCounts;
+use crate::models::revision::RevisionView;
+
+/// The post just created, for its author.
+#[derive(Serialize, Template)]
+#[template(path = "posts/create_post.html")]
+pub struct PostCreated<'a> {
+    pub post: &'a Post,
+    pub reactions: &'a ReactionCounts,
+    pub attachments: &'a [Attachment],
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/create_reply.html")]
+pub struct ReplyCreated<'a> {
+    pub post: &'a Post,
+}
+
+/// A draft or scheduled post was saved instead of published.
+#[derive(Serialize, Template)]
+#[template(path = "posts/draft_saved.html")]
+pub struct DraftSaved<'a> {
+    pub post: &'a Post,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/draft_edit.html")]
+pub struct DraftEdit<'a> {
+    pub post: &'a Post,
+    pub attachments: &'a [Attachment],
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/drafts.html")]
+pub struct Drafts<'a> {
+    pub user_id: i32,
+    pub drafts: &'a [Post],
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/edit.html")]
+pub struct Edit<'a> {
+    pub post: &'a Post,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/revisions.html")]
+pub struct Revisions<'a> {
+    pub post: &'a Post,
+    pub history: &'a [RevisionView],
+}
+
+/// A page of the home feed. The first one comes with the feed around it.
+#[derive(Serialize, Template)]
+#[template(path = "posts/feed.html")]
+pub struct FeedPage<'a> {
+    pub user_id: i32,
+    pub first_page: bool,
+    pub page: &'a Page<PostCard>,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/search.html")]
+pub struct Search<'a> {
+    pub q: &'a str,
+    pub hits: &'a [PostSearchHit],
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/thread.html")]
+pub struct Thread<'a> {
+    pub thread: &'a PostThreadNode,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/reactions.html")]
+pub struct Reactions<'a> {
+    pub post_id: Uuid,
+    pub reactions: &'a ReactionCounts,
+}
+
+// what `/posts/ws` pushes, see `PostsEvent`
+
+/// A new post for the live feeds.
+#[derive(Serialize, Template)]
+#[template(path = "posts/ws_post.html")]
+pub struct WsPost<'a> {
+    pub post: &'a Post,
+    pub reactions: &'a ReactionCounts,
+    pub attachments: &'a [Attachment],
+}
+
+/// A new post for the home feed.
+#[derive(Serialize, Template)]
+#[template(path = "posts/ws_feed_post.html")]
+pub struct WsFeedPost<'a> {
+    pub post: &'a Post,
+    pub reactions: &'a ReactionCounts,
+    pub attachments: &'a [Attachment],
+}
+
+/// A new reply for an open thread.
+#[derive(Serialize, Template)]
+#[template(path = "posts/ws_reply.html")]
+pub struct WsReply<'a> {
+    pub post: &'a Post,
+    pub reactions: &'a ReactionCounts,
+    pub attachments: &'a [Attachment],
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/ws_reactions.html")]
+pub struct WsReactions<'a> {
+    pub post_id: Uuid,
+    pub reactions: &'a ReactionCounts,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "posts/ws_retracted.html")]
+pub struct WsRetracted {
+    pub post_id: Uuid,
+}
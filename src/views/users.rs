@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+
+use macros::Template;
+use serde::Serialize;
+
+use crate::models::user::User;
+
+#[derive(Serialize, Template)]
+#[template(path = "users/get.html")]
+pub struct UserList<'a> {
+    pub users: &'a [User],
+    /// the follow buttons are for this user, none without one
+    pub acting_user_id: Option<i32>,
+    /// who of `users` the acting user follows
+    pub followed: &'a HashSet<i32>,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "users/create.html")]
+pub struct UserCreated<'a> {
+    pub id: i32,
+    pub email: &'a str,
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "users/follow_button.html")]
+pub struct FollowButton {
+    pub user_id: i32,
+    pub following: bool,
+}
+
+/// The followers of `user_id`, or who they follow, as told by `direction`.
+#[derive(Serialize, Template)]
+#[template(path = "users/follows.html")]
+pub struct Follows<'a> {
+    pub user_id: i32,
+    pub direction: &'a str,
+    pub users: &'a [User],
+}
+
+#[derive(Serialize, Template)]
+#[template(path = "users/avatar.html")]
+pub struct Avatar<'a> {
+    pub user_id: i32,
+    pub avatar_hash: &'a str,
+}